@@ -1,24 +1,76 @@
-use data_scraper::services::mobile_bg_scraper::{scrape, update};
+use data_scraper::config::rates::{import_rates, RATES, RATES_FILE};
+use data_scraper::config::run_options::{Command, RunOptions};
+use data_scraper::config::selectors::{Selectors, SELECTORS_FILE};
+use data_scraper::services::dealer_migration::migrate_dealers;
+use data_scraper::services::mobile_bg_scraper::{listing, scrape, update};
+use data_scraper::services::parse_file::parse_file;
+use data_scraper::services::price_backfill::backfill_prices;
+use data_scraper::services::run_status::RunStatus;
 use data_scraper::utils::helpers::configure_log4rs;
-use data_scraper::LOG_CONFIG;
+use data_scraper::{CONFIG, LOG_CONFIG};
 use log::{error, info};
 
 #[tokio::main]
 async fn main() {
     configure_log4rs(&LOG_CONFIG);
     info!("Starting scraper");
+    if let Err(e) = Selectors::from_file(SELECTORS_FILE) {
+        error!("{}", e);
+        std::process::exit(1);
+    }
     let args = std::env::args().collect::<Vec<String>>();
     info!("Using arguments: {:?}", args);
-    if args.len() == 2 && args[1] == "update" {
-        info!("Updating scraped data...");
-        update().await.unwrap_or_else(|e| {
-            error!("Failed to update data: {}", e);
-        });
-    } else {
-        info!("Scraping the latest adverts...");
-        scrape().await.unwrap_or_else(|e| {
-            error!("Failed to scrape data: {}", e);
-        });
-    }
+    let options = RunOptions::from_args(&args);
+    info!("Cache mode: {:?}", options.cache_mode);
+    let result = match options.command {
+        Command::Update => {
+            info!("Updating scraped data...");
+            update(&options).await
+        }
+        Command::Listing => {
+            info!("Taking a snapshot of the listing pages...");
+            listing(&options).await
+        }
+        Command::BackfillPrices => {
+            info!("Back-filling the normalized prices...");
+            backfill_prices(CONFIG.get_data_dir(), &RATES)
+        }
+        Command::MigrateDealers => {
+            info!("Migrating the dealer flags...");
+            migrate_dealers(CONFIG.get_data_dir())
+        }
+        Command::ParseFile => match &options.file {
+            Some(file) => parse_file(file, &options.kind),
+            None => {
+                Err("Usage: scraper parse-file <page.html> [--kind details|listing|search]".into())
+            }
+        },
+        Command::ImportRates => match (&options.file, options.currency.parse()) {
+            (Some(file), Ok(currency)) => import_rates(file, currency, RATES_FILE).map(|added| {
+                info!(
+                    "Added {} {} rates to {}",
+                    added, options.currency, RATES_FILE
+                );
+                RunStatus::Completed
+            }),
+            (None, _) => Err("Usage: scraper import-rates <export.csv> [--currency USD]".into()),
+            (_, Err(e)) => Err(e.into()),
+        },
+        Command::Scrape => {
+            info!("Scraping the latest adverts...");
+            scrape(&options).await
+        }
+    };
+    let exit_code = match result {
+        Ok(status) => {
+            info!("Run status: {}", status);
+            status.exit_code()
+        }
+        Err(e) => {
+            error!("Run status: failed ({})", e);
+            1
+        }
+    };
     info!("Scraper finished");
+    std::process::exit(exit_code);
 }
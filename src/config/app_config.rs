@@ -2,6 +2,8 @@ use std::{fs::File, io::Read};
 
 use serde::{Deserialize, Serialize};
 
+use crate::BROWSER_USER_AGENT;
+
 #[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct AppConfig {
     #[serde(rename = "downloaded-data-dir")]
@@ -10,6 +12,218 @@ pub struct AppConfig {
     log4rs_config: String,
     #[serde(rename = "num-threads")]
     num_threads: usize,
+    #[serde(default)]
+    http: HttpConfig,
+    #[serde(default)]
+    retry: RetryConfig,
+    #[serde(default, rename = "rate-limit")]
+    rate_limit: RateLimitConfig,
+    #[serde(default)]
+    cache: CacheConfig,
+    #[serde(default, rename = "circuit-breaker")]
+    circuit_breaker: CircuitBreakerConfig,
+    #[serde(default)]
+    warc: WarcConfig,
+    #[serde(default)]
+    robots: RobotsConfig,
+    #[serde(default, rename = "parser-health")]
+    parser_health: ParserHealthConfig,
+    #[serde(default)]
+    images: ImagesConfig,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct HttpConfig {
+    #[serde(rename = "user-agent")]
+    pub user_agent: String,
+    #[serde(rename = "timeout-secs")]
+    pub timeout_secs: u64,
+    #[serde(rename = "connect-timeout-secs")]
+    pub connect_timeout_secs: u64,
+    #[serde(rename = "pool-max-idle-per-host")]
+    pub pool_max_idle_per_host: usize,
+    #[serde(rename = "pool-idle-timeout-secs")]
+    pub pool_idle_timeout_secs: u64,
+    pub gzip: bool,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        HttpConfig {
+            user_agent: BROWSER_USER_AGENT.to_string(),
+            timeout_secs: 30,
+            connect_timeout_secs: 10,
+            pool_max_idle_per_host: 16,
+            pool_idle_timeout_secs: 90,
+            gzip: true,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct RetryConfig {
+    #[serde(rename = "max-attempts")]
+    pub max_attempts: u32,
+    #[serde(rename = "initial-backoff-millis")]
+    pub initial_backoff_millis: u64,
+    #[serde(rename = "max-backoff-millis")]
+    pub max_backoff_millis: u64,
+    pub multiplier: f64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig {
+            max_attempts: 4,
+            initial_backoff_millis: 500,
+            max_backoff_millis: 10_000,
+            multiplier: 2.0,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct BucketConfig {
+    #[serde(rename = "requests-per-second")]
+    pub requests_per_second: f64,
+    pub burst: u32,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub listing: BucketConfig,
+    pub details: BucketConfig,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            listing: BucketConfig {
+                requests_per_second: 1.0,
+                burst: 1,
+            },
+            details: BucketConfig {
+                requests_per_second: 10.0,
+                burst: 5,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct CacheConfig {
+    pub enabled: bool,
+    #[serde(rename = "ttl-hours")]
+    pub ttl_hours: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            enabled: false,
+            ttl_hours: 24,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct CircuitBreakerConfig {
+    pub threshold: u32,
+    #[serde(rename = "cool-down-secs")]
+    pub cool_down_secs: u64,
+    #[serde(rename = "max-trips")]
+    pub max_trips: u32,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        CircuitBreakerConfig {
+            threshold: 5,
+            cool_down_secs: 300,
+            max_trips: 3,
+        }
+    }
+}
+
+/// Archival of the raw responses, off by default.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct WarcConfig {
+    pub enabled: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct RobotsConfig {
+    pub enabled: bool,
+    /// Product token the robots.txt groups are matched against.
+    #[serde(rename = "user-agent")]
+    pub user_agent: String,
+}
+
+impl Default for RobotsConfig {
+    fn default() -> Self {
+        RobotsConfig {
+            enabled: true,
+            user_agent: "data-scraper".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct ImagesConfig {
+    /// Downloads the photos of the scraped adverts after the run.
+    pub enabled: bool,
+    #[serde(rename = "max-per-advert")]
+    pub max_per_advert: usize,
+    #[serde(rename = "rate-limit")]
+    pub rate_limit: BucketConfig,
+}
+
+impl Default for ImagesConfig {
+    fn default() -> Self {
+        ImagesConfig {
+            enabled: false,
+            max_per_advert: 1,
+            rate_limit: BucketConfig {
+                requests_per_second: 2.0,
+                burst: 1,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct ParserHealthConfig {
+    /// A required field found on fewer of the details pages fails the run.
+    #[serde(rename = "min-hit-rate")]
+    pub min_hit_rate: f64,
+    /// Runs with fewer details pages are not judged.
+    #[serde(rename = "min-pages")]
+    pub min_pages: usize,
+    pub required: Vec<String>,
+}
+
+impl Default for ParserHealthConfig {
+    fn default() -> Self {
+        ParserHealthConfig {
+            min_hit_rate: 0.8,
+            min_pages: 20,
+            required: vec![
+                "make".to_string(),
+                "engine".to_string(),
+                "gearbox".to_string(),
+                "price".to_string(),
+            ],
+        }
+    }
 }
 
 impl AppConfig {
@@ -32,6 +246,42 @@ impl AppConfig {
     pub fn get_num_threads(&self) -> usize {
         self.num_threads
     }
+
+    pub fn get_http(&self) -> &HttpConfig {
+        &self.http
+    }
+
+    pub fn get_retry(&self) -> &RetryConfig {
+        &self.retry
+    }
+
+    pub fn get_rate_limit(&self) -> &RateLimitConfig {
+        &self.rate_limit
+    }
+
+    pub fn get_cache(&self) -> &CacheConfig {
+        &self.cache
+    }
+
+    pub fn get_circuit_breaker(&self) -> &CircuitBreakerConfig {
+        &self.circuit_breaker
+    }
+
+    pub fn get_warc(&self) -> &WarcConfig {
+        &self.warc
+    }
+
+    pub fn get_robots(&self) -> &RobotsConfig {
+        &self.robots
+    }
+
+    pub fn get_parser_health(&self) -> &ParserHealthConfig {
+        &self.parser_health
+    }
+
+    pub fn get_images(&self) -> &ImagesConfig {
+        &self.images
+    }
 }
 
 #[cfg(test)]
@@ -46,5 +296,36 @@ mod tests {
         assert_eq!("resources/data", config.get_data_dir());
         assert_eq!("config/loggers", config.get_log4rs_config());
         assert_eq!(8, config.get_num_threads());
+        assert_eq!(30, config.get_http().timeout_secs);
+        assert_eq!(10, config.get_http().connect_timeout_secs);
+        assert_eq!(16, config.get_http().pool_max_idle_per_host);
+        assert!(config.get_http().gzip);
+        assert_eq!(4, config.get_retry().max_attempts);
+        assert_eq!(500, config.get_retry().initial_backoff_millis);
+        assert_eq!(1.0, config.get_rate_limit().listing.requests_per_second);
+        assert_eq!(1, config.get_rate_limit().listing.burst);
+        assert_eq!(10.0, config.get_rate_limit().details.requests_per_second);
+        assert_eq!(5, config.get_rate_limit().details.burst);
+        assert!(!config.get_cache().enabled);
+        assert_eq!(24, config.get_cache().ttl_hours);
+        assert_eq!(5, config.get_circuit_breaker().threshold);
+        assert_eq!(300, config.get_circuit_breaker().cool_down_secs);
+        assert!(!config.get_warc().enabled);
+        assert!(config.get_robots().enabled);
+        assert_eq!("data-scraper", config.get_robots().user_agent);
+        assert_eq!(0.8, config.get_parser_health().min_hit_rate);
+        assert_eq!(4, config.get_parser_health().required.len());
+        assert!(!config.get_images().enabled);
+        assert_eq!(1, config.get_images().max_per_advert);
+    }
+
+    #[test]
+    fn test_http_defaults() {
+        let config: AppConfig = serde_yaml::from_str(
+            "downloaded-data-dir: data\nlog4rs-config-dir: loggers\nnum-threads: 1",
+        )
+        .unwrap();
+        assert_eq!(crate::BROWSER_USER_AGENT, config.get_http().user_agent);
+        assert_eq!(90, config.get_http().pool_idle_timeout_secs);
     }
 }
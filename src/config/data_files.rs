@@ -0,0 +1,68 @@
+use crate::{
+    ARCHIVE_FILE_NAME, CACHE_DIR, CREATED_ON, DELETED_FILE_NAME, DESCRIPTION_FILE_NAME,
+    ERRORS_FILE_NAME, FOR_UPDATE_FILE_NAME, IMAGES_DIR, INSALE_FILE_NAME, LISTING_FILE_NAME,
+    METADATA_FILE_NAME, REVIEW_FILE_NAME, UPDATED_FILE_NAME, UPDATED_VEHICLES_FILE_NAME, WARC_DIR,
+};
+
+/// Files read and written by the scrape and update runs.
+#[derive(Debug, Clone)]
+pub struct DataFiles {
+    pub insale: String,
+    pub archive: String,
+    pub listing: String,
+    pub metadata: String,
+    pub for_update: String,
+    pub updated: String,
+    pub updated_vehicles: String,
+    pub deleted: String,
+    pub errors: String,
+    /// Titles and locations missing from the catalogues.
+    pub review: String,
+    /// The advert texts, keyed by id.
+    pub descriptions: String,
+    pub images_dir: String,
+    pub cache_dir: String,
+    pub warc_dir: String,
+}
+
+impl Default for DataFiles {
+    fn default() -> Self {
+        DataFiles {
+            insale: INSALE_FILE_NAME.clone(),
+            archive: ARCHIVE_FILE_NAME.clone(),
+            listing: LISTING_FILE_NAME.clone(),
+            metadata: METADATA_FILE_NAME.clone(),
+            for_update: FOR_UPDATE_FILE_NAME.clone(),
+            updated: UPDATED_FILE_NAME.clone(),
+            updated_vehicles: UPDATED_VEHICLES_FILE_NAME.clone(),
+            deleted: DELETED_FILE_NAME.clone(),
+            errors: ERRORS_FILE_NAME.clone(),
+            review: REVIEW_FILE_NAME.clone(),
+            descriptions: DESCRIPTION_FILE_NAME.clone(),
+            images_dir: IMAGES_DIR.clone(),
+            cache_dir: CACHE_DIR.clone(),
+            warc_dir: WARC_DIR.clone(),
+        }
+    }
+}
+
+impl DataFiles {
+    pub fn in_dir(data_dir: &str) -> Self {
+        DataFiles {
+            insale: format!("{}/vehicle-{}.csv", data_dir, CREATED_ON.clone()),
+            archive: format!("{}/vehicle.archive.csv", data_dir),
+            listing: format!("{}/listing-{}.csv", data_dir, CREATED_ON.clone()),
+            metadata: format!("{}/meta_data.csv", data_dir),
+            for_update: format!("{}/for_update.csv", data_dir),
+            updated: format!("{}/updated.csv", data_dir),
+            updated_vehicles: format!("{}/updated_vehicle-{}.csv", data_dir, CREATED_ON.clone()),
+            deleted: format!("{}/deleted.csv", data_dir),
+            errors: format!("{}/errors_{}.csv", data_dir, CREATED_ON.clone()),
+            review: format!("{}/review-{}.csv", data_dir, CREATED_ON.clone()),
+            descriptions: format!("{}/description-{}.csv", data_dir, CREATED_ON.clone()),
+            images_dir: format!("{}/images", data_dir),
+            cache_dir: format!("{}/cache", data_dir),
+            warc_dir: format!("{}/warc", data_dir),
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use std::error::Error;
+
+use lazy_static::lazy_static;
+use log::error;
+use serde::Deserialize;
+
+pub const GAZETTEER_FILE: &str = "config/gazetteer.csv";
+
+const SETTLEMENT_PREFIXES: [&str; 5] = ["гр.", "град ", "с.", "село ", "кв."];
+const REGION_PREFIXES: [&str; 2] = ["област", "обл."];
+
+lazy_static! {
+    pub static ref GAZETTEER: Gazetteer =
+        Gazetteer::from_file(GAZETTEER_FILE).unwrap_or_else(|e| {
+            error!(
+                "Failed to load the gazetteer from {}: {}",
+                GAZETTEER_FILE, e
+            );
+            Gazetteer::default()
+        });
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Settlement {
+    settlement: String,
+    settlement_latin: String,
+    region: String,
+    region_latin: String,
+    lat: f64,
+    lon: f64,
+}
+
+impl Settlement {
+    fn is_named(&self, name: &str) -> bool {
+        same(&self.settlement, name) || same(&self.settlement_latin, name)
+    }
+
+    fn is_in(&self, region: &str) -> bool {
+        same(&self.region, region) || same(&self.region_latin, region)
+    }
+}
+
+/// A location string resolved to an oblast and a settlement.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Place {
+    pub region: String,
+    pub city: String,
+    pub lat: Option<f64>,
+    pub lon: Option<f64>,
+    /// The location as shown on the page.
+    pub raw: String,
+    /// False when the settlement is not in the gazetteer.
+    pub matched: bool,
+}
+
+/// Bulgarian oblasts and settlements, loaded from `config/gazetteer.csv`.
+#[derive(Debug, Clone, Default)]
+pub struct Gazetteer {
+    settlements: Vec<Settlement>,
+}
+
+impl Gazetteer {
+    /// Reads the settlement rows. Lines starting with `#` are comments.
+    pub fn from_file(path: &str) -> Result<Self, Box<dyn Error>> {
+        let mut reader = csv::ReaderBuilder::new()
+            .comment(Some(b'#'))
+            .from_path(path)?;
+        let settlements = reader
+            .deserialize()
+            .collect::<Result<Vec<Settlement>, csv::Error>>()?;
+        Ok(Gazetteer { settlements })
+    }
+
+    /// Reads "гр. Пловдив, област Пловдив" style locations. The oblast, when
+    /// given, picks between settlements of the same name and is kept even when
+    /// the settlement is unknown.
+    pub fn resolve(&self, location: &str) -> Place {
+        let raw = location.split_whitespace().collect::<Vec<&str>>().join(" ");
+        let parts = raw.split(',').map(|p| p.trim()).collect::<Vec<&str>>();
+        let region_hint = parts.iter().find_map(|p| region_name(p));
+        let name = parts
+            .iter()
+            .find(|p| region_name(p).is_none())
+            .map(|p| strip_prefixes(p, &SETTLEMENT_PREFIXES))
+            .unwrap_or("");
+
+        let candidates = self
+            .settlements
+            .iter()
+            .filter(|s| s.is_named(name))
+            .collect::<Vec<&Settlement>>();
+        let found = region_hint
+            .and_then(|region| candidates.iter().find(|s| s.is_in(region)))
+            .or_else(|| candidates.first());
+        match found {
+            Some(settlement) => Place {
+                region: settlement.region.clone(),
+                city: settlement.settlement.clone(),
+                lat: Some(settlement.lat),
+                lon: Some(settlement.lon),
+                raw,
+                matched: true,
+            },
+            None => Place {
+                region: region_hint
+                    .map(|region| {
+                        self.settlements
+                            .iter()
+                            .find(|s| s.is_in(region))
+                            .map(|s| s.region.clone())
+                            .unwrap_or_else(|| region.to_string())
+                    })
+                    .unwrap_or_default(),
+                city: name.to_string(),
+                raw: raw.clone(),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// The oblast of an "обл. Пазарджик" or "Пазарджик област" part.
+fn region_name(part: &str) -> Option<&str> {
+    let lower = part.to_lowercase();
+    if REGION_PREFIXES.iter().any(|r| lower.starts_with(r)) {
+        return Some(strip_prefixes(part, &REGION_PREFIXES));
+    }
+    REGION_PREFIXES
+        .iter()
+        .find(|r| lower.ends_with(*r))
+        .and_then(|r| part.get(..part.len() - r.len()))
+        .map(|name| name.trim())
+}
+
+fn strip_prefixes<'a>(text: &'a str, prefixes: &[&str]) -> &'a str {
+    let lower = text.to_lowercase();
+    prefixes
+        .iter()
+        .find(|p| lower.starts_with(*p))
+        .and_then(|p| text.get(p.len()..))
+        .unwrap_or(text)
+        .trim()
+}
+
+fn same(a: &str, b: &str) -> bool {
+    a.to_lowercase() == b.to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Gazetteer;
+
+    #[test]
+    fn test_resolve() {
+        let gazetteer = Gazetteer::from_file("config/gazetteer.csv").unwrap();
+        let sofia = gazetteer.resolve("гр. София");
+        assert_eq!(
+            ("София-град", "София"),
+            (sofia.region.as_str(), sofia.city.as_str())
+        );
+        assert!(sofia.matched);
+        assert_eq!(sofia.lat, gazetteer.resolve("София").lat);
+        assert_eq!(sofia.city, gazetteer.resolve("Sofia").city);
+
+        let plovdiv = gazetteer.resolve("гр. Пловдив, област  Пловдив");
+        assert_eq!("Пловдив", plovdiv.region);
+        assert_eq!(Some(24.750), plovdiv.lon);
+        assert_eq!("гр. Пловдив, област Пловдив", plovdiv.raw);
+
+        let unknown = gazetteer.resolve("с. Нова Махала, обл. Пазарджик");
+        assert!(!unknown.matched);
+        assert_eq!(
+            ("Пазарджик", "Нова Махала"),
+            (unknown.region.as_str(), unknown.city.as_str())
+        );
+        assert_eq!(None, unknown.lat);
+
+        let reversed = gazetteer.resolve("обл. Враца, с. Горно Пещене");
+        assert_eq!(
+            ("Враца", "Горно Пещене"),
+            (reversed.region.as_str(), reversed.city.as_str())
+        );
+        assert_eq!(
+            "Ловеч",
+            gazetteer.resolve("с. Къкрина, Ловеч област").region
+        );
+        assert_eq!(
+            "София",
+            gazetteer
+                .resolve("с. Ковачевци, обл. Sofia Province")
+                .region
+        );
+        assert_eq!("Тракия", gazetteer.resolve("с. Крум, обл. Тракия").region);
+    }
+}
@@ -0,0 +1,202 @@
+use std::{collections::BTreeMap, error::Error};
+
+use lazy_static::lazy_static;
+use log::error;
+use serde::Deserialize;
+
+pub const MAKES_FILE: &str = "config/makes.yml";
+
+lazy_static! {
+    pub static ref MAKES: MakeCatalogue =
+        MakeCatalogue::from_file(MAKES_FILE).unwrap_or_else(|e| {
+            error!("Failed to load the makes from {}: {}", MAKES_FILE, e);
+            MakeCatalogue::default()
+        });
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct MakeEntry {
+    #[serde(default)]
+    aliases: Vec<String>,
+    #[serde(default)]
+    models: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct MakesFile {
+    makes: BTreeMap<String, MakeEntry>,
+}
+
+/// The make and model read from the title of an advert.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MakeModel {
+    pub make: String,
+    pub model: String,
+    /// The rest of the title, e.g. the engine of "Audi A4 2.0 TDI".
+    pub variant: Option<String>,
+    pub title: String,
+    /// False when the make or the model is not in the catalogue and the
+    /// first two words of the title were taken instead.
+    pub matched: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Make {
+    name: String,
+    /// The name and the aliases, longest first.
+    names: Vec<String>,
+    /// Longest first.
+    models: Vec<String>,
+}
+
+/// Canonical makes and models, loaded from `config/makes.yml`.
+#[derive(Debug, Clone, Default)]
+pub struct MakeCatalogue {
+    makes: Vec<Make>,
+}
+
+impl MakeCatalogue {
+    pub fn from_file(path: &str) -> Result<Self, Box<dyn Error>> {
+        let content = std::fs::read_to_string(path)?;
+        MakeCatalogue::from_yaml(&content)
+    }
+
+    pub fn from_yaml(content: &str) -> Result<Self, Box<dyn Error>> {
+        let file: MakesFile = serde_yaml::from_str(content)?;
+        let makes = file
+            .makes
+            .into_iter()
+            .map(|(name, entry)| {
+                let mut names = entry.aliases;
+                names.push(name.clone());
+                names.sort_by_key(|n| std::cmp::Reverse(n.len()));
+                let mut models = entry.models;
+                models.sort_by_key(|m| std::cmp::Reverse(m.len()));
+                Make {
+                    name,
+                    names,
+                    models,
+                }
+            })
+            .collect();
+        Ok(MakeCatalogue { makes })
+    }
+
+    /// The longest make or alias the title starts with, then the longest
+    /// model of that make. Falls back to the first two words.
+    pub fn resolve(&self, title: &str) -> MakeModel {
+        let title = title.split_whitespace().collect::<Vec<&str>>().join(" ");
+        let found = self
+            .makes
+            .iter()
+            .flat_map(|make| make.names.iter().map(move |name| (make, name)))
+            .filter_map(|(make, name)| strip_words(&title, name).map(|rest| (make, name, rest)))
+            .max_by_key(|(_, name, _)| name.len());
+        let matched = found.and_then(|(make, _, rest)| {
+            make.models
+                .iter()
+                .find_map(|model| strip_words(rest, model).map(|variant| (make, model, variant)))
+        });
+        match matched {
+            Some((make, model, variant)) => MakeModel {
+                make: make.name.clone(),
+                model: model.clone(),
+                variant: (!variant.is_empty()).then(|| variant.to_string()),
+                title: title.clone(),
+                matched: true,
+            },
+            None => {
+                let mut words = title.splitn(3, ' ');
+                let make = match found {
+                    Some((make, _, _)) => make.name.clone(),
+                    None => words.next().unwrap_or("").to_string(),
+                };
+                if let Some((_, _, rest)) = found {
+                    words = rest.splitn(2, ' ');
+                }
+                MakeModel {
+                    make,
+                    model: words.next().unwrap_or("").to_string(),
+                    variant: words.next().map(|v| v.to_string()),
+                    title: title.clone(),
+                    matched: false,
+                }
+            }
+        }
+    }
+}
+
+/// The text after `prefix`, when the text starts with it as whole words.
+fn strip_words<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = text.get(..prefix.len())?;
+    if !head.eq_ignore_ascii_case(prefix) {
+        return None;
+    }
+    let rest = &text[prefix.len()..];
+    if rest.is_empty() {
+        return Some(rest);
+    }
+    rest.strip_prefix(' ')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MakeCatalogue, MakeModel};
+
+    fn resolve(title: &str) -> (String, String, Option<String>, bool) {
+        let catalogue = MakeCatalogue::from_file("config/makes.yml").unwrap();
+        let MakeModel {
+            make,
+            model,
+            variant,
+            matched,
+            ..
+        } = catalogue.resolve(title);
+        (make, model, variant, matched)
+    }
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(
+            (
+                "Alfa Romeo".into(),
+                "Giulia".into(),
+                Some("2.2 D".into()),
+                true
+            ),
+            resolve("Alfa Romeo  Giulia 2.2 D")
+        );
+        assert_eq!(
+            ("Land Rover".into(), "Range Rover Sport".into(), None, true),
+            resolve("Land Rover Range Rover Sport")
+        );
+        assert_eq!(
+            ("Mercedes-Benz".into(), "S".into(), Some("500".into()), true),
+            resolve("Mercedes-Benz S 500")
+        );
+        assert_eq!(
+            (
+                "Volkswagen".into(),
+                "Golf".into(),
+                Some("1.6 TDI".into()),
+                true
+            ),
+            resolve("VW Golf 1.6 TDI")
+        );
+        assert_eq!(
+            ("Audi".into(), "Q9".into(), Some("e-tron".into()), false),
+            resolve("Audi Q9 e-tron")
+        );
+        assert_eq!(
+            (
+                "Trabant".into(),
+                "601".into(),
+                Some("S de luxe".into()),
+                false
+            ),
+            resolve("Trabant 601 S de luxe")
+        );
+        let catalogue = MakeCatalogue::from_file("config/makes.yml").unwrap();
+        assert_eq!("Mercedes S 500", catalogue.resolve("Mercedes S  500").title);
+    }
+}
@@ -1,2 +1,8 @@
 pub mod app_config;
+pub mod data_files;
 pub mod equipment;
+pub mod gazetteer;
+pub mod makes;
+pub mod rates;
+pub mod run_options;
+pub mod selectors;
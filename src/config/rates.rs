@@ -0,0 +1,222 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fs::OpenOptions,
+    io::Write,
+};
+
+use chrono::NaiveDate;
+use lazy_static::lazy_static;
+use log::error;
+use serde::Deserialize;
+
+use crate::{model::enums::Currency, DATE_FORMAT};
+
+pub const RATES_FILE: &str = "config/rates.csv";
+
+/// The date formats of the exports `import_rates` reads, the BNB one first.
+const IMPORT_DATE_FORMATS: [&str; 2] = ["%d.%m.%Y", DATE_FORMAT];
+
+lazy_static! {
+    pub static ref RATES: ExchangeRates =
+        ExchangeRates::from_file(RATES_FILE).unwrap_or_else(|e| {
+            error!(
+                "Failed to load the exchange rates from {}: {}",
+                RATES_FILE, e
+            );
+            ExchangeRates::default()
+        });
+}
+
+#[derive(Debug, Deserialize)]
+struct RateRow {
+    date: String,
+    currency: Currency,
+    rate: f64,
+}
+
+/// BGN for one unit of a currency, by the day the rate took effect.
+#[derive(Debug, Clone, Default)]
+pub struct ExchangeRates {
+    rates: HashMap<Currency, BTreeMap<NaiveDate, f64>>,
+}
+
+impl ExchangeRates {
+    /// Reads `date,currency,rate` rows. Lines starting with `#` are comments.
+    pub fn from_file(path: &str) -> Result<Self, Box<dyn Error>> {
+        let mut reader = csv::ReaderBuilder::new()
+            .comment(Some(b'#'))
+            .from_path(path)?;
+        let mut rates = ExchangeRates::default();
+        for row in reader.deserialize() {
+            let row: RateRow = row?;
+            let date = NaiveDate::parse_from_str(&row.date, DATE_FORMAT)?;
+            rates
+                .rates
+                .entry(row.currency)
+                .or_default()
+                .insert(date, row.rate);
+        }
+        Ok(rates)
+    }
+
+    /// The latest rate that took effect on or before `date`.
+    pub fn rate(&self, currency: Currency, date: NaiveDate) -> Option<f64> {
+        if currency == Currency::BGN {
+            return Some(1.0);
+        }
+        self.rates
+            .get(&currency)?
+            .range(..=date)
+            .next_back()
+            .map(|(_, rate)| *rate)
+    }
+
+    pub fn convert(
+        &self,
+        amount: u32,
+        from: Currency,
+        to: Currency,
+        date: NaiveDate,
+    ) -> Option<u32> {
+        if from == to {
+            return Some(amount);
+        }
+        let bgn = amount as f64 * self.rate(from, date)?;
+        Some((bgn / self.rate(to, date)?).round() as u32)
+    }
+
+    /// The price in BGN and in EUR, `None` where a rate is missing.
+    pub fn normalize(
+        &self,
+        amount: u32,
+        currency: Currency,
+        date: NaiveDate,
+    ) -> (Option<u32>, Option<u32>) {
+        (
+            self.convert(amount, currency, Currency::BGN, date),
+            self.convert(amount, currency, Currency::EUR, date),
+        )
+    }
+}
+
+/// Appends the `currency` rates of a `date,rate` export, e.g. the daily
+/// fixings downloaded from the BNB site, to `rates_file`. Rows without a date,
+/// like the header, are skipped and so are the days the file already has.
+/// Returns the number of rates added.
+pub fn import_rates(
+    export_file: &str,
+    currency: Currency,
+    rates_file: &str,
+) -> Result<usize, Box<dyn Error>> {
+    let known = ExchangeRates::from_file(rates_file)?;
+    let known = known.rates.get(&currency);
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_path(export_file)?;
+    let mut imported = BTreeMap::new();
+    for row in reader.records() {
+        let row = row?;
+        let Some(date) = row.get(0).and_then(|d| parse_import_date(d.trim())) else {
+            continue;
+        };
+        let rate = row
+            .get(1)
+            .and_then(|r| r.trim().parse::<f64>().ok())
+            .filter(|r| *r > 0.0)
+            .ok_or_else(|| format!("Invalid {} rate on {}: {:?}", currency, date, row.get(1)))?;
+        if !known.is_some_and(|rates| rates.contains_key(&date)) {
+            imported.insert(date, rate);
+        }
+    }
+    let mut file = OpenOptions::new().append(true).open(rates_file)?;
+    for (date, rate) in imported.iter() {
+        writeln!(file, "{},{},{}", date.format(DATE_FORMAT), currency, rate)?;
+    }
+    Ok(imported.len())
+}
+
+fn parse_import_date(date: &str) -> Option<NaiveDate> {
+    IMPORT_DATE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(date, format).ok())
+}
+
+/// `created_on` of a record as a date.
+pub fn parse_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date, DATE_FORMAT).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::model::enums::Currency;
+
+    use super::{import_rates, ExchangeRates};
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_rates() {
+        let rates = ExchangeRates::from_file("resources/test-data/rates/rates.csv").unwrap();
+        assert_eq!(Some(1.86), rates.rate(Currency::USD, date("2023-10-04")));
+        assert_eq!(Some(1.80), rates.rate(Currency::USD, date("2024-01-01")));
+        assert_eq!(None, rates.rate(Currency::USD, date("2023-10-01")));
+        assert_eq!(Some(1.0), rates.rate(Currency::BGN, date("1990-01-01")));
+
+        let day = date("2023-10-03");
+        assert_eq!(
+            (Some(19558), Some(10000)),
+            rates.normalize(10000, Currency::EUR, day)
+        );
+        assert_eq!(
+            (Some(18400), Some(9408)),
+            rates.normalize(18400, Currency::BGN, day)
+        );
+        assert_eq!(
+            (Some(18600), Some(9510)),
+            rates.normalize(10000, Currency::USD, day)
+        );
+        assert_eq!(
+            (None, None),
+            rates.normalize(10000, Currency::USD, date("2020-01-01"))
+        );
+        assert!(ExchangeRates::from_file("config/rates.csv").is_ok());
+    }
+
+    #[test]
+    fn test_import_rates() {
+        let dir = std::env::temp_dir().join("data-scraper-rates");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let rates_file = dir.join("rates.csv").to_str().unwrap().to_string();
+        let export_file = dir.join("usd.csv").to_str().unwrap().to_string();
+        std::fs::copy("resources/test-data/rates/rates.csv", &rates_file).unwrap();
+        std::fs::write(
+            &export_file,
+            "Date,Rate\n03.10.2023,1.86\n06.10.2023,1.8512\n2023-10-09,1.8437\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            2,
+            import_rates(&export_file, Currency::USD, &rates_file).unwrap()
+        );
+        assert_eq!(
+            0,
+            import_rates(&export_file, Currency::USD, &rates_file).unwrap()
+        );
+        let rates = ExchangeRates::from_file(&rates_file).unwrap();
+        assert_eq!(Some(1.86), rates.rate(Currency::USD, date("2023-10-04")));
+        assert_eq!(Some(1.8512), rates.rate(Currency::USD, date("2023-10-07")));
+        assert_eq!(Some(1.8437), rates.rate(Currency::USD, date("2024-01-01")));
+
+        std::fs::write(&export_file, "06.10.2023,n/a\n").unwrap();
+        assert!(import_rates(&export_file, Currency::USD, &rates_file).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,144 @@
+use crate::{config::app_config::CacheConfig, http::cache::CacheMode, CONFIG};
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Command {
+    #[default]
+    Scrape,
+    Update,
+    /// Reads the listing pages only, without visiting the details pages.
+    Listing,
+    /// Adds the normalized prices to the existing snapshots.
+    BackfillPrices,
+    /// Rewrites the dealer flag of the existing snapshots.
+    MigrateDealers,
+    /// Runs a parser on a saved page and explains what it matched.
+    ParseFile,
+    /// Adds the rates of an exported `date,rate` file to the rates file.
+    ImportRates,
+}
+
+/// What the scraper binary has been asked to do.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct RunOptions {
+    pub command: Command,
+    pub cache_mode: CacheMode,
+    /// The page of `parse-file` and its kind, `details` by default.
+    pub file: Option<String>,
+    pub kind: String,
+    /// The currency of the `import-rates` file, `USD` by default.
+    pub currency: String,
+}
+
+impl RunOptions {
+    /// `update`, `listing`, `backfill-prices`, `migrate-dealers`,
+    /// `parse-file <page.html> [--kind details|listing|search]` and
+    /// `import-rates <export.csv> [--currency USD]` select their commands,
+    /// `--replay` serves every page from the cache and `--no-cache` bypasses
+    /// it. Without a flag the cache is used when it is enabled in the config,
+    /// except by `update`, which needs the pages as they are now.
+    pub fn from_args(args: &[String]) -> Self {
+        Self::with_cache(args, CONFIG.get_cache())
+    }
+
+    fn with_cache(args: &[String], cache: &CacheConfig) -> Self {
+        let has = |flag: &str| args.iter().skip(1).any(|a| a == flag);
+        let value_of = |flag: &str| {
+            args.iter()
+                .skip(1)
+                .skip_while(|a| *a != flag)
+                .nth(1)
+                .cloned()
+        };
+        // First, so the kind of the page is not taken for a command.
+        let command = if has("parse-file") {
+            Command::ParseFile
+        } else if has("import-rates") {
+            Command::ImportRates
+        } else if has("update") {
+            Command::Update
+        } else if has("listing") {
+            Command::Listing
+        } else if has("backfill-prices") {
+            Command::BackfillPrices
+        } else if has("migrate-dealers") {
+            Command::MigrateDealers
+        } else {
+            Command::Scrape
+        };
+        let cache_mode = if has("--replay") {
+            CacheMode::Replay
+        } else if has("--no-cache") || !cache.enabled || command == Command::Update {
+            CacheMode::Off
+        } else {
+            CacheMode::Record
+        };
+        RunOptions {
+            command,
+            cache_mode,
+            file: value_of("parse-file").or_else(|| value_of("import-rates")),
+            kind: value_of("--kind").unwrap_or_else(|| "details".to_string()),
+            currency: value_of("--currency").unwrap_or_else(|| "USD".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{config::app_config::CacheConfig, http::cache::CacheMode};
+
+    use super::{Command, RunOptions};
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_from_args() {
+        let cache = CacheConfig {
+            enabled: true,
+            ttl_hours: 24,
+        };
+        let parse = |values: &[&str]| RunOptions::with_cache(&args(values), &cache);
+        let options = parse(&["scraper"]);
+        assert_eq!(Command::Scrape, options.command);
+        assert_eq!(CacheMode::Record, options.cache_mode);
+        let options = RunOptions::with_cache(&args(&["scraper"]), &CacheConfig::default());
+        assert_eq!(CacheMode::Off, options.cache_mode);
+
+        let options = parse(&["scraper", "update"]);
+        assert_eq!(
+            (Command::Update, CacheMode::Off),
+            (options.command, options.cache_mode)
+        );
+
+        let options = parse(&["scraper", "update", "--replay"]);
+        assert_eq!(Command::Update, options.command);
+        assert_eq!(CacheMode::Replay, options.cache_mode);
+
+        let options = parse(&["scraper", "--no-cache"]);
+        assert_eq!(CacheMode::Off, options.cache_mode);
+
+        let options = parse(&["scraper", "listing"]);
+        assert_eq!(Command::Listing, options.command);
+
+        let options = parse(&["scraper", "backfill-prices"]);
+        assert_eq!(Command::BackfillPrices, options.command);
+
+        let options = parse(&["scraper", "migrate-dealers"]);
+        assert_eq!(Command::MigrateDealers, options.command);
+
+        let options = parse(&["scraper", "parse-file", "page.html", "--kind", "listing"]);
+        assert_eq!(Command::ParseFile, options.command);
+        assert_eq!(Some("page.html".to_string()), options.file);
+        assert_eq!("listing", options.kind);
+        let options = parse(&["scraper", "parse-file"]);
+        assert_eq!((None, "details"), (options.file, options.kind.as_str()));
+
+        let options = parse(&["scraper", "import-rates", "usd.csv"]);
+        assert_eq!(Command::ImportRates, options.command);
+        assert_eq!(
+            (Some("usd.csv"), "USD"),
+            (options.file.as_deref(), options.currency.as_str())
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use std::{error::Error, fmt, ops::Deref, path::Path};
+
+use lazy_static::lazy_static;
+use log::warn;
+use scraper::Selector;
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    CATEGORY_TXT, COLOUR_TXT, DISPLACEMENT_TXT, DOORS_TXT, EDITED_TXT, ENGINE_TXT,
+    EURO_STANDARD_TXT, GEARBOX_TXT, MILLAGE_TXT, NOT_FOUND_MSG, POWER_TXT, PUBLISHED_TXT, VIN_TXT,
+    YEAR_TXT,
+};
+
+pub const SELECTORS_FILE: &str = "config/selectors.yml";
+const SELECTOR_SECTIONS: [&str; 3] = ["details", "listing", "search"];
+
+lazy_static! {
+    pub static ref SELECTORS: Selectors =
+        Selectors::from_file(SELECTORS_FILE).unwrap_or_else(|e| panic!("{}", e));
+}
+
+/// A CSS selector compiled when it is read, together with its source.
+#[derive(Debug, Clone)]
+pub struct Css {
+    pub css: String,
+    selector: Selector,
+}
+
+impl Css {
+    pub fn new(css: &str) -> Result<Self, String> {
+        let selector =
+            Selector::parse(css).map_err(|e| format!("invalid selector '{}': {:?}", css, e))?;
+        Ok(Css {
+            css: css.to_string(),
+            selector,
+        })
+    }
+
+    /// For the defaults, which are known to compile.
+    fn of(css: &str) -> Self {
+        Css::new(css).unwrap()
+    }
+}
+
+impl Deref for Css {
+    type Target = Selector;
+
+    fn deref(&self) -> &Selector {
+        &self.selector
+    }
+}
+
+impl fmt::Display for Css {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.css)
+    }
+}
+
+impl<'de> Deserialize<'de> for Css {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let css = String::deserialize(deserializer)?;
+        Css::new(&css).map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct DetailsSelectors {
+    pub header: Css,
+    pub specs: Css,
+    pub price: Css,
+    pub view_count: Css,
+    /// The publication and last edit times, next to the view counter.
+    pub dates: Css,
+    pub phone: Css,
+    pub address: Css,
+    /// The name of the dealer, only shown for dealers.
+    pub dealer: Css,
+    pub dealer_link: Css,
+    pub dealer_since: Css,
+    pub sold: Css,
+    pub top: Css,
+    pub vip: Css,
+    pub equipment: Css,
+    /// The photos of the gallery, read from `data-src` or `src`.
+    pub images: Css,
+    pub description: Css,
+}
+
+impl Default for DetailsSelectors {
+    fn default() -> Self {
+        DetailsSelectors {
+            header: Css::of("h1"),
+            specs: Css::of("ul.dilarData li"),
+            price: Css::of("span#details_price"),
+            view_count: Css::of("span.advact"),
+            dates: Css::of("div.advDates"),
+            phone: Css::of("div.phone"),
+            address: Css::of("div.adress"),
+            dealer: Css::of("div.AG > strong"),
+            dealer_link: Css::of("div.AG a[href]"),
+            dealer_since: Css::of("div.AG div.since"),
+            sold: Css::of("div.kaparo"),
+            top: Css::of("td.img.TOP"),
+            vip: Css::of("td.img.VIP"),
+            equipment: Css::of("div[style*=\"margin-bottom:5px;\"]"),
+            images: Css::of("div.photos img"),
+            description: Css::of("div.moreInfo"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ListingSelectors {
+    /// One advert of a listing page.
+    pub row: Css,
+    pub link: Css,
+    pub price: Css,
+    pub promoted: Css,
+    pub sold: Css,
+    /// The production date and the mileage of a row.
+    pub summary: Css,
+    pub promoted_summary: Css,
+}
+
+impl Default for ListingSelectors {
+    fn default() -> Self {
+        ListingSelectors {
+            row: Css::of("table.tablereset"),
+            link: Css::of("td.valgtop a.mmm"),
+            price: Css::of("span.price"),
+            promoted: Css::of(
+                r#"img[alt="top"][class="noborder"], img[alt="vip"][class="noborder"]"#,
+            ),
+            sold: Css::of(r#"img[src*="kaparirano.svg"]"#),
+            summary: Css::of(r#"td[colspan="4"]"#),
+            promoted_summary: Css::of(r#"td[colspan="3"]"#),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct SearchSelectors {
+    pub description: Css,
+    pub page_numbers: Css,
+    pub slink: Css,
+}
+
+impl Default for SearchSelectors {
+    fn default() -> Self {
+        SearchSelectors {
+            description: Css::of("meta[name=description]"),
+            page_numbers: Css::of("a.pageNumbers"),
+            slink: Css::of("input[name=slink]"),
+        }
+    }
+}
+
+/// Texts of the site the parsers look for.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Labels {
+    pub not_found: String,
+    pub power: String,
+    pub gearbox: String,
+    pub engine: String,
+    pub millage: String,
+    pub year: String,
+    pub category: String,
+    pub colour: String,
+    pub euro_standard: String,
+    pub displacement: String,
+    pub doors: String,
+    pub vin: String,
+    pub published: String,
+    pub edited: String,
+}
+
+impl Default for Labels {
+    fn default() -> Self {
+        Labels {
+            not_found: NOT_FOUND_MSG.to_string(),
+            power: POWER_TXT.to_string(),
+            gearbox: GEARBOX_TXT.to_string(),
+            engine: ENGINE_TXT.to_string(),
+            millage: MILLAGE_TXT.to_string(),
+            year: YEAR_TXT.to_string(),
+            category: CATEGORY_TXT.to_string(),
+            colour: COLOUR_TXT.to_string(),
+            euro_standard: EURO_STANDARD_TXT.to_string(),
+            displacement: DISPLACEMENT_TXT.to_string(),
+            doors: DOORS_TXT.to_string(),
+            vin: VIN_TXT.to_string(),
+            published: PUBLISHED_TXT.to_string(),
+            edited: EDITED_TXT.to_string(),
+        }
+    }
+}
+
+/// The selectors and labels of `config/selectors.yml`. A missing entry takes
+/// the built-in default.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Selectors {
+    pub details: DetailsSelectors,
+    pub listing: ListingSelectors,
+    pub search: SearchSelectors,
+    pub labels: Labels,
+}
+
+impl Selectors {
+    /// Every selector is compiled, the error names the first one that does
+    /// not. Without the file the defaults are used.
+    pub fn from_file(path: &str) -> Result<Self, Box<dyn Error>> {
+        if !Path::new(path).exists() {
+            warn!("No {}, using the default selectors", path);
+            return Ok(Selectors::default());
+        }
+        let content = std::fs::read_to_string(path)?;
+        Selectors::from_yaml(&content).map_err(|e| format!("Invalid {}: {}", path, e).into())
+    }
+
+    pub fn from_yaml(content: &str) -> Result<Self, Box<dyn Error>> {
+        let value: serde_yaml::Value = serde_yaml::from_str(content)?;
+        validate(&value)?;
+        Ok(serde_yaml::from_value(value)?)
+    }
+}
+
+/// Compiles every selector of the file, naming the first one that fails.
+fn validate(value: &serde_yaml::Value) -> Result<(), String> {
+    for section in SELECTOR_SECTIONS {
+        let Some(entries) = value.get(section).and_then(|v| v.as_mapping()) else {
+            continue;
+        };
+        for (key, css) in entries {
+            let key = key.as_str().unwrap_or_default();
+            let css = css
+                .as_str()
+                .ok_or_else(|| format!("{}.{}: not a string", section, key))?;
+            Css::new(css).map_err(|e| format!("{}.{}: {}", section, key, e))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Selectors, SELECTORS_FILE};
+
+    #[test]
+    fn test_from_file() {
+        let selectors = Selectors::from_file(SELECTORS_FILE).unwrap();
+        let defaults = Selectors::default();
+        assert_eq!(defaults.details.specs.css, selectors.details.specs.css);
+        assert_eq!(
+            defaults.listing.promoted.css,
+            selectors.listing.promoted.css
+        );
+        assert_eq!(defaults.labels, selectors.labels);
+        assert!(Selectors::from_file("config/missing.yml").is_ok());
+    }
+
+    #[test]
+    fn test_invalid() {
+        let selectors = Selectors::from_yaml("details:\n  phone: div.mobile\n").unwrap();
+        assert_eq!("div.mobile", selectors.details.phone.css);
+        assert_eq!("h1", selectors.details.header.css);
+
+        let error = Selectors::from_yaml("listing:\n  row: \"table[\"\n")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("listing.row"), "{}", error);
+        assert!(error.contains("table["), "{}", error);
+    }
+}
@@ -0,0 +1,118 @@
+use std::fmt;
+
+use crate::config::selectors::SELECTORS;
+
+use super::{fetcher::Page, rate_limiter::PageKind, robots::ROBOTS_PATH};
+
+/// Markers of the captcha and "checking your browser" pages served instead
+/// of the requested one.
+const CHALLENGE_MARKERS: [&str; 6] = [
+    "captcha",
+    "cf-challenge",
+    "challenge-platform",
+    "checking your browser",
+    "unusual traffic",
+    "access denied",
+];
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BlockKind {
+    /// 403 response.
+    Forbidden,
+    /// 429 response.
+    Throttled,
+    /// A captcha or an interstitial page.
+    Challenge,
+    /// A page without the content expected for the requested url.
+    Unexpected,
+}
+
+impl fmt::Display for BlockKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlockKind::Forbidden => write!(f, "forbidden"),
+            BlockKind::Throttled => write!(f, "throttled"),
+            BlockKind::Challenge => write!(f, "captcha or interstitial page"),
+            BlockKind::Unexpected => write!(f, "unexpected page"),
+        }
+    }
+}
+
+/// Tells a block page apart from a genuine one.
+///
+/// A page is checked for the challenge markers only when it misses the content
+/// of the requested page, so a details page with a contact form captcha is
+/// still a details page.
+pub fn detect(page: &Page) -> Option<BlockKind> {
+    match page.status {
+        403 => return Some(BlockKind::Forbidden),
+        429 => return Some(BlockKind::Throttled),
+        400.. => return None,
+        _ => {}
+    }
+    if page.html.contains(&SELECTORS.labels.not_found) || has_expected_content(page) {
+        return None;
+    }
+    let html = page.html.to_lowercase();
+    if CHALLENGE_MARKERS.iter().any(|marker| html.contains(marker)) {
+        Some(BlockKind::Challenge)
+    } else {
+        Some(BlockKind::Unexpected)
+    }
+}
+
+fn has_expected_content(page: &Page) -> bool {
+    if page.url.ends_with(ROBOTS_PATH) {
+        return true;
+    }
+    match PageKind::of(&page.url) {
+        PageKind::Details => page.html.contains("<h1"),
+        PageKind::Listing => page.html.contains("name=\"description\""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::http::{fetcher::Page, mock::fixture};
+
+    use super::{detect, BlockKind};
+
+    const DETAILS: &str = "https://www.mobile.bg/pcgi/mobile.cgi?act=4&adv=1";
+    const LISTING: &str = "https://www.mobile.bg/pcgi/mobile.cgi?act=3&f1=1";
+
+    fn page(url: &str, status: u16, html: &str) -> Page {
+        Page {
+            url: url.to_string(),
+            status,
+            html: html.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_genuine_pages() {
+        assert_eq!(None, detect(&page(DETAILS, 200, &fixture("details.html"))));
+        assert_eq!(
+            None,
+            detect(&page(DETAILS, 200, &fixture("not_found.html")))
+        );
+        assert_eq!(None, detect(&page(LISTING, 200, &fixture("listing.html"))));
+        assert_eq!(None, detect(&page(LISTING, 200, &fixture("search.html"))));
+        assert_eq!(None, detect(&page(DETAILS, 503, "")));
+    }
+
+    #[test]
+    fn test_block_pages() {
+        let captcha = "<html><body><div class=\"g-recaptcha\"></div></body></html>";
+        assert_eq!(
+            Some(BlockKind::Challenge),
+            detect(&page(LISTING, 200, captcha))
+        );
+        assert_eq!(
+            Some(BlockKind::Unexpected),
+            detect(&page(DETAILS, 200, &fixture("search.html")))
+        );
+        assert_eq!(Some(BlockKind::Forbidden), detect(&page(DETAILS, 403, "")));
+        assert_eq!(Some(BlockKind::Throttled), detect(&page(LISTING, 429, "")));
+    }
+}
@@ -0,0 +1,323 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
+
+use chrono::Utc;
+use futures::future::BoxFuture;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    client::normalize_url,
+    fetcher::{FetchError, Fetcher, Page},
+    rate_limiter::PageKind,
+    retry::{classify, FetchStats, Outcome},
+};
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum CacheMode {
+    /// Every page is fetched from the site.
+    #[default]
+    Off,
+    /// Fresh pages are served from the cache, the rest are fetched and stored.
+    Record,
+    /// Every page is served from the cache, regardless of its age. A page
+    /// that is not in the cache is an error.
+    Replay,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    key: String,
+    url: String,
+    status: u16,
+    fetched_at: i64,
+    html: String,
+    #[serde(default)]
+    encoding: String,
+}
+
+/// Pages stored on disk as json files, one per normalized url.
+#[derive(Debug, Clone)]
+pub struct PageCache {
+    dir: PathBuf,
+    ttl: Option<Duration>,
+}
+
+impl PageCache {
+    /// A `ttl` of `None` keeps the pages forever.
+    pub fn new(dir: &str, ttl: Option<Duration>) -> Self {
+        PageCache {
+            dir: PathBuf::from(dir),
+            ttl,
+        }
+    }
+
+    pub fn from_hours(dir: &str, ttl_hours: u64) -> Self {
+        let ttl = (ttl_hours > 0).then(|| Duration::from_secs(ttl_hours * 3600));
+        PageCache::new(dir, ttl)
+    }
+
+    /// Returns the page stored for `url`. Expired pages are returned only when
+    /// `ignore_ttl` is set.
+    pub async fn get(&self, url: &str, ignore_ttl: bool) -> Option<Page> {
+        let key = cache_key(url);
+        let path = self.path_of(&key);
+        let content = tokio::fs::read_to_string(&path).await.ok()?;
+        let entry: CacheEntry = match serde_json::from_str(&content) {
+            Ok(entry) => entry,
+            Err(e) => {
+                warn!("Ignoring corrupted cache entry {:?}: {}", path, e);
+                return None;
+            }
+        };
+        if entry.key != key {
+            return None;
+        }
+        if !ignore_ttl && self.is_expired(entry.fetched_at) {
+            debug!("Cache entry for {} expired", url);
+            return None;
+        }
+        Some(Page {
+            url: url.to_string(),
+            status: entry.status,
+            html: entry.html,
+            encoding: entry.encoding,
+        })
+    }
+
+    pub async fn put(&self, page: &Page) -> std::io::Result<()> {
+        self.put_at(page, Utc::now().timestamp()).await
+    }
+
+    async fn put_at(&self, page: &Page, fetched_at: i64) -> std::io::Result<()> {
+        let key = cache_key(&page.url);
+        let path = self.path_of(&key);
+        let entry = CacheEntry {
+            key,
+            url: page.url.clone(),
+            status: page.status,
+            fetched_at,
+            html: page.html.clone(),
+            encoding: page.encoding.clone(),
+        };
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        // Written aside and renamed, so a concurrent reader never sees half
+        // of an entry.
+        let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+        tokio::fs::write(&tmp, serde_json::to_vec(&entry)?).await?;
+        tokio::fs::rename(&tmp, &path).await
+    }
+
+    fn is_expired(&self, fetched_at: i64) -> bool {
+        match self.ttl {
+            Some(ttl) => Utc::now().timestamp() - fetched_at >= ttl.as_secs() as i64,
+            None => false,
+        }
+    }
+
+    fn path_of(&self, key: &str) -> PathBuf {
+        let hash = format!("{:016x}", fnv1a(key.as_bytes()));
+        Path::new(&self.dir)
+            .join(&hash[..2])
+            .join(format!("{}.json", hash))
+    }
+}
+
+/// The url with a sorted query and without empty parameters. The `slink` of
+/// a details url only names the search it was found by, so it is dropped and
+/// the same advert is cached once.
+pub fn cache_key(url: &str) -> String {
+    let url = normalize_url(url);
+    let (base, query) = match url.split_once('?') {
+        Some((base, query)) => (base, query),
+        None => return url,
+    };
+    let details = PageKind::of(&url) == PageKind::Details;
+    let mut params = query
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .filter(|p| !(details && p.starts_with("slink=")))
+        .collect::<Vec<&str>>();
+    params.sort_unstable();
+    format!("{}?{}", base, params.join("&"))
+}
+
+/// Stable across builds, unlike the hasher of the standard library.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Serves the pages from a `PageCache` before asking the inner fetcher.
+///
+/// Only the pages that would not be retried are stored, i.e. the successful
+/// and the not found ones.
+pub struct CachedFetcher {
+    inner: Option<Arc<dyn Fetcher>>,
+    cache: PageCache,
+    stats: Arc<FetchStats>,
+}
+
+impl CachedFetcher {
+    pub fn record(inner: Arc<dyn Fetcher>, cache: PageCache, stats: Arc<FetchStats>) -> Self {
+        CachedFetcher {
+            inner: Some(inner),
+            cache,
+            stats,
+        }
+    }
+
+    pub fn replay(cache: PageCache, stats: Arc<FetchStats>) -> Self {
+        CachedFetcher {
+            inner: None,
+            cache,
+            stats,
+        }
+    }
+
+    async fn fetch_cached(&self, url: &str) -> Result<Page, FetchError> {
+        if let Some(page) = self.cache.get(url, self.inner.is_none()).await {
+            debug!("Cache hit for {}", url);
+            self.stats.cache_hits.fetch_add(1, Ordering::SeqCst);
+            return Ok(page);
+        }
+        let inner = match &self.inner {
+            Some(inner) => inner,
+            None => return Err(FetchError::NotCached(url.to_string())),
+        };
+        let result = inner.fetch(url).await;
+        if let Ok(page) = &result {
+            if matches!(classify(&result), Outcome::Success | Outcome::NotFound) {
+                if let Err(e) = self.cache.put(page).await {
+                    warn!("Failed to cache {}: {}", url, e);
+                }
+            }
+        }
+        result
+    }
+}
+
+impl Fetcher for CachedFetcher {
+    fn fetch<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<Page, FetchError>> {
+        Box::pin(self.fetch_cached(url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use futures::future::BoxFuture;
+
+    use crate::http::{
+        fetcher::{FetchError, Fetcher, Page},
+        retry::FetchStats,
+    };
+
+    use super::{cache_key, CachedFetcher, PageCache};
+
+    struct CountingFetcher {
+        status: u16,
+        calls: AtomicUsize,
+    }
+
+    impl Fetcher for CountingFetcher {
+        fn fetch<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<Page, FetchError>> {
+            Box::pin(async move {
+                self.calls.fetch_add(1, Ordering::SeqCst);
+                Ok(Page {
+                    url: url.to_string(),
+                    status: self.status,
+                    html: "<html>Audi</html>".to_string(),
+                    ..Default::default()
+                })
+            })
+        }
+    }
+
+    fn cache_dir(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("data-scraper-cache-{}", name));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir.to_str().unwrap().to_string()
+    }
+
+    fn counting(status: u16) -> Arc<CountingFetcher> {
+        Arc::new(CountingFetcher {
+            status,
+            calls: AtomicUsize::new(0),
+        })
+    }
+
+    #[test]
+    fn test_cache_key() {
+        assert_eq!(
+            "https://www.mobile.bg/pcgi/mobile.cgi?act=4&adv=1",
+            cache_key("//www.mobile.bg/pcgi/mobile.cgi?adv=1&&act=4&slink=abc")
+        );
+        assert_eq!(
+            "https://www.mobile.bg/pcgi/mobile.cgi?act=3&f1=2&slink=abc",
+            cache_key("https://www.mobile.bg/pcgi/mobile.cgi?slink=abc&act=3&f1=2")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let dir = cache_dir("record");
+        let url = "//www.mobile.bg/pcgi/mobile.cgi?act=4&adv=1&slink=abc";
+        let stats = Arc::new(FetchStats::default());
+        let inner = counting(200);
+        let fetcher =
+            CachedFetcher::record(inner.clone(), PageCache::from_hours(&dir, 1), stats.clone());
+        fetcher.fetch(url).await.unwrap();
+        let page = fetcher
+            .fetch("//www.mobile.bg/pcgi/mobile.cgi?act=4&adv=1&slink=xyz")
+            .await
+            .unwrap();
+        assert_eq!(1, inner.calls.load(Ordering::SeqCst));
+        assert_eq!(1, stats.cache_hits.load(Ordering::SeqCst));
+        assert_eq!("<html>Audi</html>", page.html);
+
+        let replay = CachedFetcher::replay(PageCache::from_hours(&dir, 1), stats.clone());
+        assert_eq!(200, replay.fetch(url).await.unwrap().status);
+        let missing = replay
+            .fetch("//www.mobile.bg/pcgi/mobile.cgi?act=4&adv=2")
+            .await;
+        assert!(matches!(missing, Err(FetchError::NotCached(_))));
+    }
+
+    #[tokio::test]
+    async fn test_expired_and_transient() {
+        let dir = cache_dir("expired");
+        let url = "//www.mobile.bg/pcgi/mobile.cgi?act=3&f1=1";
+        let cache = PageCache::new(&dir, Some(Duration::from_secs(60)));
+        let page = Page {
+            url: url.to_string(),
+            status: 200,
+            html: "old".to_string(),
+            ..Default::default()
+        };
+        cache.put_at(&page, 0).await.unwrap();
+        assert!(cache.get(url, false).await.is_none());
+        assert_eq!("old", cache.get(url, true).await.unwrap().html);
+
+        let inner = counting(503);
+        let stats = Arc::new(FetchStats::default());
+        let fetcher = CachedFetcher::record(inner.clone(), cache.clone(), stats);
+        let url = "//www.mobile.bg/pcgi/mobile.cgi?act=3&f1=2";
+        fetcher.fetch(url).await.unwrap();
+        fetcher.fetch(url).await.unwrap();
+        assert_eq!(2, inner.calls.load(Ordering::SeqCst));
+    }
+}
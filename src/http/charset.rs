@@ -0,0 +1,136 @@
+use encoding_rs::{Encoding, WINDOWS_1251};
+
+/// The encoding mobile.bg has always served its pages in.
+pub const DEFAULT_ENCODING: &Encoding = WINDOWS_1251;
+
+/// How far into the body a `<meta>` charset declaration is looked for.
+const META_SNIFF_LEN: usize = 2048;
+
+/// Encoding named by the `charset` parameter of a Content-Type header.
+pub fn from_content_type(content_type: &str) -> Option<&'static Encoding> {
+    content_type
+        .split(';')
+        .skip(1)
+        .filter_map(|param| param.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+        .and_then(|(_, label)| Encoding::for_label(label.trim().trim_matches('"').as_bytes()))
+}
+
+/// Encoding declared by `<meta charset="...">` or by
+/// `<meta http-equiv="Content-Type" content="...; charset=...">`.
+pub fn from_meta(body: &[u8]) -> Option<&'static Encoding> {
+    let head = &body[..body.len().min(META_SNIFF_LEN)];
+    let head = String::from_utf8_lossy(head).to_ascii_lowercase();
+    head.split("<meta").skip(1).find_map(|tag| {
+        let tag = &tag[..tag.find('>').unwrap_or(tag.len())];
+        let (_, rest) = tag.split_once("charset=")?;
+        let label = rest
+            .trim_start_matches(['"', '\''])
+            .split(|c: char| c == '"' || c == '\'' || c == ';' || c == '/' || c.is_whitespace())
+            .next()?;
+        Encoding::for_label(label.as_bytes())
+    })
+}
+
+/// The header wins over the markup, Windows-1251 is the fallback.
+pub fn detect(content_type: Option<&str>, body: &[u8]) -> &'static Encoding {
+    content_type
+        .and_then(from_content_type)
+        .or_else(|| from_meta(body))
+        .unwrap_or(DEFAULT_ENCODING)
+}
+
+/// Decodes the body into UTF-8 and returns the name of the encoding used. A
+/// byte order mark overrides the detected encoding.
+pub fn decode(content_type: Option<&str>, body: &[u8]) -> (String, &'static str) {
+    let (html, encoding, _) = detect(content_type, body).decode(body);
+    (html.into_owned(), encoding.name())
+}
+
+#[cfg(test)]
+mod tests {
+    use encoding_rs::{UTF_8, WINDOWS_1251};
+    use mockito::Matcher;
+
+    use crate::{
+        http::{
+            fetcher::Fetcher,
+            mock::{fixture, MockFetcher},
+        },
+        NOT_FOUND_MSG,
+    };
+
+    use super::{decode, detect, from_content_type, from_meta};
+
+    #[test]
+    fn test_from_content_type() {
+        assert_eq!(Some(UTF_8), from_content_type("text/html; charset=UTF-8"));
+        assert_eq!(
+            Some(WINDOWS_1251),
+            from_content_type("text/html;Charset=\"cp1251\"")
+        );
+        assert_eq!(None, from_content_type("text/html"));
+        assert_eq!(None, from_content_type("text/html; charset=unknown"));
+    }
+
+    #[test]
+    fn test_from_meta() {
+        assert_eq!(
+            Some(UTF_8),
+            from_meta(b"<html><head><meta charset=\"utf-8\"></head>")
+        );
+        assert_eq!(
+            Some(WINDOWS_1251),
+            from_meta(
+                b"<META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=windows-1251\">"
+            )
+        );
+        assert_eq!(
+            None,
+            from_meta(b"<meta name=\"description\" content=\"x\">")
+        );
+    }
+
+    #[test]
+    fn test_decode() {
+        let (utf8, _, _) = UTF_8.encode(NOT_FOUND_MSG);
+        let (cp1251, _, _) = WINDOWS_1251.encode(NOT_FOUND_MSG);
+
+        let (html, encoding) = decode(Some("text/html; charset=utf-8"), &utf8);
+        assert_eq!((NOT_FOUND_MSG, "UTF-8"), (html.as_str(), encoding));
+
+        let with_meta = [b"<meta charset=utf-8>".as_slice(), &utf8].concat();
+        let (html, encoding) = decode(Some("text/html"), &with_meta);
+        assert!(html.contains(NOT_FOUND_MSG));
+        assert_eq!("UTF-8", encoding);
+
+        let (html, encoding) = decode(None, &cp1251);
+        assert_eq!((NOT_FOUND_MSG, "windows-1251"), (html.as_str(), encoding));
+        assert_eq!(WINDOWS_1251, detect(None, &cp1251));
+    }
+
+    #[tokio::test]
+    async fn test_encoding_recorded() {
+        let mut fetcher = MockFetcher::new().await;
+        let details = Matcher::UrlEncoded("act".into(), "4".into());
+        let html = fixture("not_found.html");
+        fetcher
+            .mock_encoded(details, 200, &html, UTF_8, "text/html; charset=utf-8")
+            .await;
+        fetcher.mock_search(&html).await;
+
+        let page = fetcher
+            .fetch("//www.mobile.bg/pcgi/mobile.cgi?act=4&adv=1")
+            .await
+            .unwrap();
+        assert_eq!("UTF-8", page.encoding);
+        assert!(page.html.contains(NOT_FOUND_MSG));
+
+        let page = fetcher
+            .fetch("//www.mobile.bg/pcgi/mobile.cgi?act=3&f1=1")
+            .await
+            .unwrap();
+        assert_eq!("windows-1251", page.encoding);
+        assert!(page.html.contains(NOT_FOUND_MSG));
+    }
+}
@@ -0,0 +1,215 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures::future::BoxFuture;
+use log::{error, info, warn};
+use tokio::time::Instant;
+
+use crate::config::app_config::CircuitBreakerConfig;
+
+use super::{
+    block::{self, BlockKind},
+    fetcher::{FetchError, Fetcher, Page},
+};
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    consecutive: u32,
+    trips: u32,
+    paused_until: Option<Instant>,
+    aborted: Option<String>,
+}
+
+/// Pauses every fetcher sharing it after `threshold` consecutive block pages.
+///
+/// Once the cool-down is over the requests are resumed. After `max-trips`
+/// cool-downs without a genuine page in between the run is aborted and every
+/// request fails straight away.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    cool_down: Duration,
+    max_trips: u32,
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cool_down: Duration, max_trips: u32) -> Self {
+        CircuitBreaker {
+            threshold: threshold.max(1),
+            cool_down,
+            max_trips,
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    pub fn from_config(config: &CircuitBreakerConfig) -> Self {
+        CircuitBreaker::new(
+            config.threshold,
+            Duration::from_secs(config.cool_down_secs),
+            config.max_trips,
+        )
+    }
+
+    /// Waits for the end of the cool-down, if there is one.
+    pub async fn wait(&self) -> Result<(), FetchError> {
+        loop {
+            let paused_until = {
+                let state = self.state.lock().unwrap();
+                if let Some(reason) = &state.aborted {
+                    return Err(FetchError::Aborted(reason.clone()));
+                }
+                state.paused_until
+            };
+            match paused_until {
+                Some(until) if until > Instant::now() => tokio::time::sleep_until(until).await,
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.trips > 0 {
+            info!("Genuine pages are served again");
+        }
+        state.consecutive = 0;
+        state.trips = 0;
+    }
+
+    pub fn record_block(&self, url: &str, kind: BlockKind) {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        // The requests sent before the pause started are not counted again.
+        if state.aborted.is_some() || state.paused_until.is_some_and(|until| until > now) {
+            return;
+        }
+        state.consecutive += 1;
+        warn!(
+            "Block page ({}) for {}, {} in a row",
+            kind, url, state.consecutive
+        );
+        if state.consecutive < self.threshold {
+            return;
+        }
+        state.consecutive = 0;
+        state.trips += 1;
+        if state.trips > self.max_trips {
+            let reason = format!(
+                "{} consecutive block pages after {} cool-downs, last one {}",
+                self.threshold, self.max_trips, kind
+            );
+            error!("Aborting the run: {}", reason);
+            state.aborted = Some(reason);
+        } else {
+            warn!(
+                "Pausing all requests for {:?} (cool-down {}/{})",
+                self.cool_down, state.trips, self.max_trips
+            );
+            state.paused_until = Some(now + self.cool_down);
+        }
+    }
+
+    /// The reason the run was aborted for, if it was.
+    pub fn aborted(&self) -> Option<String> {
+        self.state.lock().unwrap().aborted.clone()
+    }
+}
+
+/// Turns the block pages of the inner fetcher into errors and reports them to
+/// the circuit breaker, so they are neither parsed nor cached.
+pub struct CircuitBreakerFetcher {
+    inner: Arc<dyn Fetcher>,
+    breaker: Arc<CircuitBreaker>,
+}
+
+impl CircuitBreakerFetcher {
+    pub fn new(inner: Arc<dyn Fetcher>, breaker: Arc<CircuitBreaker>) -> Self {
+        CircuitBreakerFetcher { inner, breaker }
+    }
+
+    async fn fetch_guarded(&self, url: &str) -> Result<Page, FetchError> {
+        self.breaker.wait().await?;
+        let page = self.inner.fetch(url).await?;
+        match block::detect(&page) {
+            Some(kind) => {
+                self.breaker.record_block(url, kind);
+                Err(FetchError::Blocked(kind))
+            }
+            None => {
+                if page.status < 400 {
+                    self.breaker.record_success();
+                }
+                Ok(page)
+            }
+        }
+    }
+}
+
+impl Fetcher for CircuitBreakerFetcher {
+    fn fetch<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<Page, FetchError>> {
+        Box::pin(self.fetch_guarded(url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use mockito::Matcher;
+    use tokio::time::Instant;
+
+    use crate::http::{
+        block::BlockKind,
+        fetcher::{FetchError, Fetcher},
+        mock::MockFetcher,
+    };
+
+    use super::{CircuitBreaker, CircuitBreakerFetcher};
+
+    const DETAILS: &str = "//www.mobile.bg/pcgi/mobile.cgi?act=4&adv=1";
+
+    #[tokio::test]
+    async fn test_pause_and_resume() {
+        let breaker = CircuitBreaker::new(2, Duration::from_millis(100), 1);
+        breaker.record_block(DETAILS, BlockKind::Challenge);
+        breaker.record_success();
+        breaker.record_block(DETAILS, BlockKind::Challenge);
+        let start = Instant::now();
+        breaker.wait().await.unwrap();
+        assert!(start.elapsed() < Duration::from_millis(50));
+
+        breaker.record_block(DETAILS, BlockKind::Challenge);
+        // Ignored, the breaker is already paused.
+        breaker.record_block(DETAILS, BlockKind::Challenge);
+        breaker.wait().await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert_eq!(None, breaker.aborted());
+
+        breaker.record_block(DETAILS, BlockKind::Forbidden);
+        breaker.record_block(DETAILS, BlockKind::Forbidden);
+        assert!(breaker.aborted().is_some());
+        assert!(matches!(breaker.wait().await, Err(FetchError::Aborted(_))));
+    }
+
+    #[tokio::test]
+    async fn test_block_page_is_an_error() {
+        let mut mock = MockFetcher::with_fixtures().await;
+        let captcha = "<html><body><div class=\"g-recaptcha\"></div></body></html>";
+        mock.mock_page(Matcher::UrlEncoded("adv".into(), "2".into()), 200, captcha)
+            .await;
+        let breaker = Arc::new(CircuitBreaker::new(5, Duration::from_secs(1), 1));
+        let fetcher = CircuitBreakerFetcher::new(Arc::new(mock), breaker.clone());
+
+        assert!(fetcher.fetch(DETAILS).await.is_ok());
+        let blocked = fetcher
+            .fetch("//www.mobile.bg/pcgi/mobile.cgi?act=4&adv=2")
+            .await;
+        assert!(matches!(
+            blocked,
+            Err(FetchError::Blocked(BlockKind::Challenge))
+        ));
+    }
+}
@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+use lazy_static::lazy_static;
+
+use crate::{config::app_config::HttpConfig, CONFIG};
+
+lazy_static! {
+    pub static ref BLOCKING_CLIENT: reqwest::blocking::Client =
+        build_blocking_client(CONFIG.get_http()).unwrap();
+}
+
+pub fn build_client(config: &HttpConfig) -> reqwest::Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .user_agent(config.user_agent.as_str())
+        .timeout(Duration::from_secs(config.timeout_secs))
+        .connect_timeout(Duration::from_secs(config.connect_timeout_secs))
+        .pool_max_idle_per_host(config.pool_max_idle_per_host)
+        .pool_idle_timeout(Duration::from_secs(config.pool_idle_timeout_secs))
+        .gzip(config.gzip)
+        .build()
+}
+
+pub fn build_blocking_client(config: &HttpConfig) -> reqwest::Result<reqwest::blocking::Client> {
+    reqwest::blocking::Client::builder()
+        .user_agent(config.user_agent.as_str())
+        .timeout(Duration::from_secs(config.timeout_secs))
+        .connect_timeout(Duration::from_secs(config.connect_timeout_secs))
+        .pool_max_idle_per_host(config.pool_max_idle_per_host)
+        .pool_idle_timeout(Duration::from_secs(config.pool_idle_timeout_secs))
+        .gzip(config.gzip)
+        .build()
+}
+
+/// The site links are protocol relative (`//www.mobile.bg/...`).
+pub fn normalize_url(url: &str) -> String {
+    if url.starts_with("//") {
+        format!("https:{}", url)
+    } else {
+        url.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::normalize_url;
+
+    #[test]
+    fn test_normalize_url() {
+        assert_eq!(
+            "https://www.mobile.bg/pcgi/mobile.cgi?act=4&adv=1",
+            normalize_url("//www.mobile.bg/pcgi/mobile.cgi?act=4&adv=1")
+        );
+        assert_eq!(
+            "https://www.mobile.bg/pcgi/mobile.cgi?act=3",
+            normalize_url("https://www.mobile.bg/pcgi/mobile.cgi?act=3")
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use std::{fmt, sync::Arc};
+
+use chrono::Utc;
+
+use futures::future::BoxFuture;
+use log::{debug, warn};
+
+use crate::{config::app_config::HttpConfig, CONFIG};
+
+use super::{
+    block::BlockKind,
+    charset,
+    client::{build_client, normalize_url},
+    warc::{ArchivedResponse, WarcWriter},
+};
+
+#[derive(Debug)]
+pub enum FetchError {
+    Http(reqwest::Error),
+    Status(u16),
+    Blocked(BlockKind),
+    Aborted(String),
+    NotCached(String),
+    Other(String),
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchError::Http(e) => write!(f, "http error: {}", e),
+            FetchError::Status(status) => write!(f, "unexpected status: {}", status),
+            FetchError::Blocked(kind) => write!(f, "blocked: {}", kind),
+            FetchError::Aborted(reason) => write!(f, "run aborted: {}", reason),
+            FetchError::NotCached(url) => write!(f, "not in the cache: {}", url),
+            FetchError::Other(e) => write!(f, "fetch error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for FetchError {}
+
+impl From<reqwest::Error> for FetchError {
+    fn from(e: reqwest::Error) -> Self {
+        FetchError::Http(e)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Page {
+    pub url: String,
+    pub status: u16,
+    pub html: String,
+    /// Name of the encoding the body was decoded from.
+    pub encoding: String,
+}
+
+/// Source of html pages for the parsers and the scraping pipeline.
+pub trait Fetcher: Send + Sync {
+    fn fetch<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<Page, FetchError>>;
+}
+
+/// Fetches the pages from the live site.
+///
+/// The client is built once, so connections are pooled and reused by all the
+/// requests made through the fetcher and its clones.
+#[derive(Debug, Clone)]
+pub struct HttpFetcher {
+    client: reqwest::Client,
+    archive: Option<Arc<WarcWriter>>,
+}
+
+impl HttpFetcher {
+    pub fn new() -> Self {
+        HttpFetcher::from_config(CONFIG.get_http()).unwrap()
+    }
+
+    pub fn from_config(config: &HttpConfig) -> Result<Self, FetchError> {
+        Ok(HttpFetcher {
+            client: build_client(config)?,
+            archive: None,
+        })
+    }
+
+    /// Every response, whatever its status, is also written to `archive`.
+    pub fn with_archive(mut self, archive: Arc<WarcWriter>) -> Self {
+        self.archive = Some(archive);
+        self
+    }
+
+    async fn get(&self, url: &str) -> Result<Page, FetchError> {
+        let response = self.client.get(normalize_url(url)).send().await?;
+        let status = response.status().as_u16();
+        let content_type = content_type(response.headers());
+        let headers = self
+            .archive
+            .as_ref()
+            .map(|_| header_pairs(response.headers()));
+        let body: Vec<u8> = response.bytes().await?.to_vec();
+        debug!("body: {}", body.len());
+        if let (Some(archive), Some(headers)) = (&self.archive, headers) {
+            let archived = ArchivedResponse {
+                url: normalize_url(url),
+                status,
+                headers,
+                body: body.clone(),
+                fetched_at: Utc::now(),
+            };
+            if let Err(e) = archive.write(&archived) {
+                warn!("Failed to archive {}: {}", url, e);
+            }
+        }
+        let (html, encoding) = charset::decode(content_type.as_deref(), &body);
+        debug!("response: {} ({})", html.len(), encoding);
+        Ok(Page {
+            url: url.to_string(),
+            status,
+            html,
+            encoding: encoding.to_string(),
+        })
+    }
+}
+
+impl Default for HttpFetcher {
+    fn default() -> Self {
+        HttpFetcher::new()
+    }
+}
+
+impl Fetcher for HttpFetcher {
+    fn fetch<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<Page, FetchError>> {
+        Box::pin(self.get(url))
+    }
+}
+
+pub fn content_type(headers: &reqwest::header::HeaderMap) -> Option<String> {
+    headers
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string())
+}
+
+fn header_pairs(headers: &reqwest::header::HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.to_string(),
+                String::from_utf8_lossy(value.as_bytes()).to_string(),
+            )
+        })
+        .collect()
+}
+
+/// Decodes a body that came without a Content-Type header.
+pub fn decode_html(body: &[u8]) -> String {
+    charset::decode(None, body).0
+}
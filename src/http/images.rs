@@ -0,0 +1,171 @@
+use std::{fs, path::PathBuf};
+
+use log::{debug, info, warn};
+
+use crate::{
+    config::app_config::{HttpConfig, ImagesConfig},
+    model::records::MobileRecord,
+};
+
+use super::{
+    client::{build_client, normalize_url},
+    fetcher::FetchError,
+    rate_limiter::TokenBucket,
+};
+
+/// Saves the thumbnails of the advert photos under `<dir>/<id>/<n>.<ext>`, at
+/// most `max-per-advert` of them and at the rate of its own token bucket. The
+/// thumbnails already saved are not downloaded again.
+pub struct ImageDownloader {
+    client: reqwest::Client,
+    bucket: TokenBucket,
+    dir: PathBuf,
+    max_per_advert: usize,
+}
+
+impl ImageDownloader {
+    pub fn new(http: &HttpConfig, config: &ImagesConfig, dir: &str) -> Result<Self, FetchError> {
+        Ok(ImageDownloader {
+            client: build_client(http)?,
+            bucket: TokenBucket::new(&config.rate_limit),
+            dir: PathBuf::from(dir),
+            max_per_advert: config.max_per_advert,
+        })
+    }
+
+    pub fn path_of(&self, id: &str, index: usize, url: &str) -> PathBuf {
+        let extension = url
+            .rsplit('/')
+            .next()
+            .and_then(|name| name.split('?').next())
+            .and_then(|name| name.rsplit_once('.'))
+            .map(|(_, extension)| extension)
+            .filter(|extension| extension.len() <= 4)
+            .unwrap_or("jpg");
+        self.dir.join(id).join(format!("{}.{}", index, extension))
+    }
+
+    /// Takes the full size photo urls of the advert and returns the number of
+    /// thumbnails saved.
+    pub async fn download(&self, id: &str, urls: &[String]) -> Result<usize, FetchError> {
+        let mut saved = 0;
+        for (index, url) in urls.iter().take(self.max_per_advert).enumerate() {
+            let url = &thumbnail_url(url);
+            let path = self.path_of(id, index, url);
+            if path.exists() {
+                continue;
+            }
+            let wait = self.bucket.reserve();
+            if !wait.is_zero() {
+                tokio::time::sleep(wait).await;
+            }
+            let response = self.client.get(normalize_url(url)).send().await?;
+            if !response.status().is_success() {
+                return Err(FetchError::Status(response.status().as_u16()));
+            }
+            let body = response.bytes().await?;
+            debug!("image {}: {} bytes", url, body.len());
+            fs::create_dir_all(self.dir.join(id))
+                .and_then(|_| fs::write(&path, &body))
+                .map_err(|e| FetchError::Other(e.to_string()))?;
+            saved += 1;
+        }
+        Ok(saved)
+    }
+
+    /// Downloads the photos of every record. A failed advert is logged and
+    /// skipped.
+    pub async fn download_all(&self, records: &[MobileRecord]) -> usize {
+        let mut saved = 0;
+        for record in records {
+            match self.download(&record.id, &record.image_urls()).await {
+                Ok(count) => saved += count,
+                Err(e) => warn!("Failed to download the images of {}: {}", record.id, e),
+            }
+        }
+        info!("Downloaded {} images to {:?}", saved, self.dir);
+        saved
+    }
+}
+
+/// The small variant of a gallery photo: `.../big/1.webp` is shown as
+/// `.../small/1.webp` in the thumbnail strip. A url without a size segment is
+/// returned as it is.
+pub fn thumbnail_url(url: &str) -> String {
+    url.split('/')
+        .map(|segment| match segment.strip_prefix("big") {
+            Some(suffix) if suffix.chars().all(|c| c.is_ascii_digit()) => {
+                format!("small{}", suffix)
+            }
+            _ => segment.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use mockito::Server;
+
+    use crate::{
+        config::app_config::{BucketConfig, ImagesConfig},
+        CONFIG,
+    };
+
+    use super::{thumbnail_url, ImageDownloader};
+
+    #[tokio::test]
+    async fn test_download() {
+        let mut server = Server::new_async().await;
+        let full_size = server
+            .mock("GET", "/photos/big/1.webp")
+            .expect(0)
+            .create_async()
+            .await;
+        let thumbnail = server
+            .mock("GET", "/photos/small/1.webp")
+            .with_body([1u8, 2, 3])
+            .expect(1)
+            .create_async()
+            .await;
+        let dir = std::env::temp_dir().join("data-scraper-images");
+        let _ = fs::remove_dir_all(&dir);
+        let config = ImagesConfig {
+            enabled: true,
+            max_per_advert: 1,
+            rate_limit: BucketConfig {
+                requests_per_second: 0.0,
+                burst: 1,
+            },
+        };
+        let images =
+            ImageDownloader::new(CONFIG.get_http(), &config, dir.to_str().unwrap()).unwrap();
+        let urls = vec![
+            format!("{}/photos/big/1.webp", server.url()),
+            format!("{}/photos/big/2.webp", server.url()),
+        ];
+        assert_eq!(1, images.download("42", &urls).await.unwrap());
+        assert_eq!(0, images.download("42", &urls).await.unwrap());
+        assert_eq!(
+            vec![1u8, 2, 3],
+            fs::read(dir.join("42").join("0.webp")).unwrap()
+        );
+        assert_eq!(
+            dir.join("7").join("0.jpg"),
+            images.path_of("7", 0, "//cdn.mobile.bg/photos/1?size=small")
+        );
+        thumbnail.assert_async().await;
+        full_size.assert_async().await;
+        assert_eq!(
+            "https://cdn.mobile.bg/mobile/photosmob/small1/2.webp",
+            thumbnail_url("https://cdn.mobile.bg/mobile/photosmob/big1/2.webp")
+        );
+        assert_eq!(
+            "//cdn.mobile.bg/bigcars/1.webp",
+            thumbnail_url("//cdn.mobile.bg/bigcars/1.webp")
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
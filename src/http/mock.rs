@@ -0,0 +1,155 @@
+use encoding_rs::{Encoding, WINDOWS_1251};
+use futures::future::BoxFuture;
+use mockito::{Matcher, Mock, Server, ServerGuard};
+
+use crate::utils::helpers::read_file_from;
+
+use super::fetcher::{FetchError, Fetcher, HttpFetcher, Page};
+
+pub const FIXTURES_DIR: &str = "resources/test-data/html";
+pub const ROBOTS_FIXTURES_DIR: &str = "resources/test-data/robots";
+const SITE_PREFIX: &str = "//www.mobile.bg";
+
+/// Serves saved pages from a local mockito server instead of mobile.bg.
+///
+/// Requests for `www.mobile.bg` are redirected to the local server, so the
+/// urls built by the scraper can be used as they are. When several mocks
+/// match a request the most recently registered one wins.
+pub struct MockFetcher {
+    server: ServerGuard,
+    mocks: Vec<Mock>,
+    http: HttpFetcher,
+}
+
+impl MockFetcher {
+    pub async fn new() -> Self {
+        MockFetcher {
+            server: Server::new_async().await,
+            mocks: vec![],
+            http: HttpFetcher::new(),
+        }
+    }
+
+    /// Serves `search.html`, `listing.html` and `details.html` for every
+    /// search, listing and details request and `mobile.bg.txt` as robots.txt.
+    pub async fn with_fixtures() -> Self {
+        let mut fetcher = MockFetcher::new().await;
+        fetcher.add_fixtures().await;
+        fetcher
+    }
+
+    pub async fn add_fixtures(&mut self) {
+        self.mock_search(&fixture("search.html")).await;
+        self.mock_listing(&fixture("listing.html")).await;
+        self.mock_details(None, &fixture("details.html")).await;
+        self.mock_robots(200, &robots_fixture("mobile.bg.txt"))
+            .await;
+    }
+
+    pub async fn mock_robots(&mut self, status: usize, content: &str) {
+        let mock = self
+            .server
+            .mock("GET", "/robots.txt")
+            .with_status(status)
+            .with_header("content-type", "text/plain")
+            .with_body(content)
+            .expect_at_least(0)
+            .create_async()
+            .await;
+        self.mocks.push(mock);
+    }
+
+    /// Search requests are the listing requests without `slink`.
+    pub async fn mock_search(&mut self, html: &str) {
+        let query = Matcher::UrlEncoded("act".into(), "3".into());
+        self.mock_page(query, 200, html).await;
+    }
+
+    pub async fn mock_listing(&mut self, html: &str) {
+        let query = Matcher::AllOf(vec![
+            Matcher::UrlEncoded("act".into(), "3".into()),
+            Matcher::Regex("slink=".into()),
+        ]);
+        self.mock_page(query, 200, html).await;
+    }
+
+    pub async fn mock_details(&mut self, id: Option<&str>, html: &str) {
+        let mut matchers = vec![Matcher::UrlEncoded("act".into(), "4".into())];
+        if let Some(id) = id {
+            matchers.push(Matcher::UrlEncoded("adv".into(), id.into()));
+        }
+        self.mock_page(Matcher::AllOf(matchers), 200, html).await;
+    }
+
+    /// Serves `html` encoded as Windows-1251, the same way mobile.bg does.
+    pub async fn mock_page(&mut self, query: Matcher, status: usize, html: &str) {
+        let content_type = "text/html; charset=windows-1251";
+        self.mock_encoded(query, status, html, WINDOWS_1251, content_type)
+            .await;
+    }
+
+    /// Serves `html` encoded as `encoding` with the given Content-Type.
+    pub async fn mock_encoded(
+        &mut self,
+        query: Matcher,
+        status: usize,
+        html: &str,
+        encoding: &'static Encoding,
+        content_type: &str,
+    ) {
+        let (body, _, _) = encoding.encode(html);
+        let mock = self
+            .server
+            .mock("GET", "/pcgi/mobile.cgi")
+            .match_query(query)
+            .with_status(status)
+            .with_header("content-type", content_type)
+            .with_body(body)
+            .expect_at_least(0)
+            .create_async()
+            .await;
+        self.mocks.push(mock);
+    }
+
+    /// Answers the first `times` matching requests with `status`. Afterwards
+    /// the requests fall back to the mocks registered after this one.
+    pub async fn mock_failures(&mut self, query: Matcher, status: usize, times: usize) {
+        let mock = self
+            .server
+            .mock("GET", "/pcgi/mobile.cgi")
+            .match_query(query)
+            .with_status(status)
+            .expect(times)
+            .create_async()
+            .await;
+        self.mocks.push(mock);
+    }
+
+    pub fn local_url(&self, url: &str) -> String {
+        let url = url.trim_start_matches("https:").trim_start_matches("http:");
+        match url.strip_prefix(SITE_PREFIX) {
+            Some(path) => format!("{}{}", self.server.url(), path),
+            None => url.to_string(),
+        }
+    }
+}
+
+impl Fetcher for MockFetcher {
+    fn fetch<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<Page, FetchError>> {
+        Box::pin(async move {
+            let mut page = self.http.fetch(&self.local_url(url)).await?;
+            page.url = url.to_string();
+            Ok(page)
+        })
+    }
+}
+
+pub fn fixture(file_name: &str) -> String {
+    read_file_from(FIXTURES_DIR, file_name)
+        .unwrap_or_else(|e| panic!("Missing fixture {}: {}", file_name, e))
+}
+
+pub fn robots_fixture(file_name: &str) -> String {
+    read_file_from(ROBOTS_FIXTURES_DIR, file_name)
+        .unwrap_or_else(|e| panic!("Missing fixture {}: {}", file_name, e))
+}
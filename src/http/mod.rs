@@ -0,0 +1,13 @@
+pub mod block;
+pub mod cache;
+pub mod charset;
+pub mod circuit_breaker;
+pub mod client;
+pub mod fetcher;
+pub mod images;
+#[cfg(test)]
+pub mod mock;
+pub mod rate_limiter;
+pub mod retry;
+pub mod robots;
+pub mod warc;
@@ -0,0 +1,335 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use futures::future::BoxFuture;
+use log::{debug, info};
+use reqwest::Url;
+
+use crate::{
+    config::app_config::{BucketConfig, RateLimitConfig},
+    ACTION_DETAILS,
+};
+
+use super::{
+    client::normalize_url,
+    fetcher::{FetchError, Fetcher, Page},
+    robots::RobotsCache,
+};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum PageKind {
+    Listing,
+    Details,
+}
+
+impl PageKind {
+    pub fn of(url: &str) -> Self {
+        if url.contains(ACTION_DETAILS) {
+            PageKind::Details
+        } else {
+            PageKind::Listing
+        }
+    }
+}
+
+#[derive(Debug)]
+struct BucketState {
+    tokens: f64,
+    last: Instant,
+}
+
+/// Token bucket refilled with `requests-per-second` tokens up to `burst`.
+/// A rate of zero disables the limit.
+#[derive(Debug)]
+pub struct TokenBucket {
+    rate: f64,
+    burst: f64,
+    state: Mutex<BucketState>,
+}
+
+impl TokenBucket {
+    pub fn new(config: &BucketConfig) -> Self {
+        let burst = config.burst.max(1) as f64;
+        TokenBucket {
+            rate: config.requests_per_second,
+            burst,
+            state: Mutex::new(BucketState {
+                tokens: burst,
+                last: Instant::now(),
+            }),
+        }
+    }
+
+    /// Takes a token and returns how long the caller has to wait for it.
+    /// Tokens are reserved in advance, so the waiting callers are served in
+    /// the order they came.
+    pub fn reserve(&self) -> Duration {
+        if self.rate <= 0.0 {
+            return Duration::ZERO;
+        }
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        let elapsed = now.duration_since(state.last).as_secs_f64();
+        state.tokens = (state.tokens + elapsed * self.rate).min(self.burst);
+        state.last = now;
+        state.tokens -= 1.0;
+        if state.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-state.tokens / self.rate)
+        }
+    }
+
+    pub async fn acquire(&self) {
+        let wait = self.reserve();
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[derive(Debug)]
+struct HostBuckets {
+    listing: TokenBucket,
+    details: TokenBucket,
+    /// One request per `Crawl-delay` of the host, shared by both kinds of
+    /// page, with the delay it was made for.
+    crawl_delay: Mutex<Option<(Duration, Arc<TokenBucket>)>>,
+}
+
+impl HostBuckets {
+    /// The bucket of the current `Crawl-delay`, made again when the robots
+    /// rules of the host came with another delay.
+    fn crawl_bucket(&self, host: &str, crawl_delay: Option<Duration>) -> Option<Arc<TokenBucket>> {
+        let mut current = self.crawl_delay.lock().unwrap();
+        let Some(delay) = crawl_delay.filter(|d| !d.is_zero()) else {
+            *current = None;
+            return None;
+        };
+        match current.as_ref() {
+            Some((known, bucket)) if *known == delay => Some(bucket.clone()),
+            known => {
+                info!("Using the crawl-delay of {:?} for {}", delay, host);
+                let bucket = Arc::new(TokenBucket::new(&BucketConfig {
+                    requests_per_second: 1.0 / delay.as_secs_f64(),
+                    burst: 1,
+                }));
+                // A changed delay starts a full delay after the last request.
+                if known.is_some() {
+                    bucket.reserve();
+                }
+                *current = Some((delay, bucket.clone()));
+                Some(bucket)
+            }
+        }
+    }
+}
+
+/// Separate listing and details budgets for every host, and a shared one
+/// when the host asks for a `Crawl-delay`.
+#[derive(Debug)]
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    hosts: Mutex<HashMap<String, Arc<HostBuckets>>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        RateLimiter {
+            config,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn acquire(&self, url: &str) {
+        self.acquire_with_delay(url, None).await
+    }
+
+    /// Same as `acquire`, but the listing and details pages of the host also
+    /// take turns at one request per `crawl_delay`, the one of the robots
+    /// rules loaded last.
+    pub async fn acquire_with_delay(&self, url: &str, crawl_delay: Option<Duration>) {
+        let host = host_of(url);
+        let buckets = self.buckets(&host);
+        let wait = match PageKind::of(url) {
+            PageKind::Listing => buckets.listing.reserve(),
+            PageKind::Details => buckets.details.reserve(),
+        };
+        let wait = match buckets.crawl_bucket(&host, crawl_delay) {
+            Some(bucket) => wait.max(bucket.reserve()),
+            None => wait,
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    fn buckets(&self, host: &str) -> Arc<HostBuckets> {
+        let mut hosts = self.hosts.lock().unwrap();
+        hosts
+            .entry(host.to_string())
+            .or_insert_with_key(|host| {
+                debug!("New rate limit buckets for {}", host);
+                Arc::new(HostBuckets {
+                    listing: TokenBucket::new(&self.config.listing),
+                    details: TokenBucket::new(&self.config.details),
+                    crawl_delay: Mutex::new(None),
+                })
+            })
+            .clone()
+    }
+}
+
+pub fn host_of(url: &str) -> String {
+    Url::parse(&normalize_url(url))
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_string()))
+        .unwrap_or_default()
+}
+
+/// Waits for the rate limiter before every request of the inner fetcher.
+pub struct RateLimitedFetcher {
+    inner: Arc<dyn Fetcher>,
+    limiter: RateLimiter,
+    robots: Option<Arc<RobotsCache>>,
+}
+
+impl RateLimitedFetcher {
+    pub fn new(inner: Arc<dyn Fetcher>, config: RateLimitConfig) -> Self {
+        RateLimitedFetcher {
+            inner,
+            limiter: RateLimiter::new(config),
+            robots: None,
+        }
+    }
+
+    /// Honours the `Crawl-delay` of the robots.txt of every host.
+    pub fn with_robots(mut self, robots: Arc<RobotsCache>) -> Self {
+        self.robots = Some(robots);
+        self
+    }
+}
+
+impl Fetcher for RateLimitedFetcher {
+    fn fetch<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<Page, FetchError>> {
+        Box::pin(async move {
+            let crawl_delay = match &self.robots {
+                Some(robots) => robots.crawl_delay(url).await,
+                None => None,
+            };
+            self.limiter.acquire_with_delay(url, crawl_delay).await;
+            self.inner.fetch(url).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::config::app_config::{BucketConfig, RateLimitConfig};
+
+    use super::{host_of, PageKind, RateLimiter, TokenBucket};
+
+    const DETAILS: &str = "https://www.mobile.bg/pcgi/mobile.cgi?act=4&adv=1";
+    const LISTING: &str = "https://www.mobile.bg/pcgi/mobile.cgi?act=3&f1=1";
+
+    fn bucket(requests_per_second: f64, burst: u32) -> BucketConfig {
+        BucketConfig {
+            requests_per_second,
+            burst,
+        }
+    }
+
+    #[test]
+    fn test_reserve() {
+        let bucket = TokenBucket::new(&bucket(10.0, 2));
+        assert_eq!(Duration::ZERO, bucket.reserve());
+        assert_eq!(Duration::ZERO, bucket.reserve());
+        let wait = bucket.reserve();
+        assert!(wait > Duration::from_millis(80) && wait <= Duration::from_millis(100));
+        let wait = bucket.reserve();
+        assert!(wait > Duration::from_millis(180) && wait <= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_unlimited() {
+        let bucket = TokenBucket::new(&bucket(0.0, 1));
+        for _ in 0..10 {
+            assert_eq!(Duration::ZERO, bucket.reserve());
+        }
+    }
+
+    #[test]
+    fn test_page_kind() {
+        assert_eq!(
+            PageKind::Details,
+            PageKind::of("https://www.mobile.bg/pcgi/mobile.cgi?act=4&adv=1")
+        );
+        assert_eq!(
+            PageKind::Listing,
+            PageKind::of("https://www.mobile.bg/pcgi/mobile.cgi?act=3&f1=1")
+        );
+        assert_eq!("www.mobile.bg", host_of("//www.mobile.bg/pcgi/mobile.cgi"));
+    }
+
+    #[tokio::test]
+    async fn test_separate_budgets() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            listing: bucket(20.0, 1),
+            details: bucket(1000.0, 10),
+        });
+        let start = Instant::now();
+        for _ in 0..10 {
+            limiter
+                .acquire("https://www.mobile.bg/pcgi/mobile.cgi?act=4&adv=1")
+                .await;
+        }
+        assert!(start.elapsed() < Duration::from_millis(50));
+        let start = Instant::now();
+        for _ in 0..3 {
+            limiter
+                .acquire("https://www.mobile.bg/pcgi/mobile.cgi?act=3&f1=1")
+                .await;
+        }
+        assert!(start.elapsed() >= Duration::from_millis(95));
+    }
+
+    #[tokio::test]
+    async fn test_crawl_delay() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            listing: bucket(1000.0, 10),
+            details: bucket(1000.0, 10),
+        });
+        let delay = Some(Duration::from_millis(50));
+        let start = Instant::now();
+        // Listing and details pages share the delay of the host.
+        for url in [DETAILS, LISTING, DETAILS, LISTING] {
+            limiter.acquire_with_delay(url, delay).await;
+        }
+        assert!(start.elapsed() >= Duration::from_millis(145));
+
+        // Reloaded robots rules with a longer delay apply at once.
+        let start = Instant::now();
+        for url in [DETAILS, LISTING] {
+            limiter
+                .acquire_with_delay(url, Some(Duration::from_millis(100)))
+                .await;
+        }
+        assert!(start.elapsed() >= Duration::from_millis(195));
+
+        // A configured rate slower than the crawl-delay is kept.
+        let limiter = RateLimiter::new(RateLimitConfig {
+            listing: bucket(10.0, 1),
+            details: bucket(1000.0, 10),
+        });
+        let start = Instant::now();
+        for _ in 0..3 {
+            limiter.acquire_with_delay(LISTING, delay).await;
+        }
+        assert!(start.elapsed() >= Duration::from_millis(195));
+    }
+}
@@ -0,0 +1,291 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use futures::future::BoxFuture;
+use log::{error, info, warn};
+use rand::Rng;
+
+use crate::config::{app_config::RetryConfig, selectors::SELECTORS};
+
+use super::fetcher::{FetchError, Fetcher, Page};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Outcome {
+    Success,
+    NotFound,
+    Transient,
+    Permanent,
+}
+
+/// Counters of the requests made through a `RetryFetcher` and of the pages
+/// served by a `CachedFetcher`.
+#[derive(Debug, Default)]
+pub struct FetchStats {
+    pub requests: AtomicUsize,
+    pub attempts: AtomicUsize,
+    pub retries: AtomicUsize,
+    pub succeeded: AtomicUsize,
+    pub not_found: AtomicUsize,
+    pub failed: AtomicUsize,
+    pub cache_hits: AtomicUsize,
+}
+
+impl fmt::Display for FetchStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "requests: {}, attempts: {}, retries: {}, succeeded: {}, not found: {}, failed: {}, cache hits: {}",
+            self.requests.load(Ordering::SeqCst),
+            self.attempts.load(Ordering::SeqCst),
+            self.retries.load(Ordering::SeqCst),
+            self.succeeded.load(Ordering::SeqCst),
+            self.not_found.load(Ordering::SeqCst),
+            self.failed.load(Ordering::SeqCst),
+            self.cache_hits.load(Ordering::SeqCst),
+        )
+    }
+}
+
+/// Retries the transient failures of the inner fetcher with a jittered
+/// exponential backoff.
+///
+/// A not found advert is returned as a page, so the parser can tell it apart
+/// from a page that could not be fetched at all.
+pub struct RetryFetcher {
+    inner: Arc<dyn Fetcher>,
+    config: RetryConfig,
+    stats: Arc<FetchStats>,
+}
+
+impl RetryFetcher {
+    pub fn new(inner: Arc<dyn Fetcher>, config: RetryConfig, stats: Arc<FetchStats>) -> Self {
+        RetryFetcher {
+            inner,
+            config,
+            stats,
+        }
+    }
+
+    async fn fetch_with_retry(&self, url: &str) -> Result<Page, FetchError> {
+        self.stats.requests.fetch_add(1, Ordering::SeqCst);
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            self.stats.attempts.fetch_add(1, Ordering::SeqCst);
+            let result = self.inner.fetch(url).await;
+            match classify(&result) {
+                Outcome::Success => {
+                    self.stats.succeeded.fetch_add(1, Ordering::SeqCst);
+                    if attempt > 1 {
+                        info!("Fetched {} after {} attempts", url, attempt);
+                    }
+                    return result;
+                }
+                Outcome::NotFound => {
+                    self.stats.not_found.fetch_add(1, Ordering::SeqCst);
+                    return result;
+                }
+                Outcome::Permanent => {
+                    self.stats.failed.fetch_add(1, Ordering::SeqCst);
+                    let e = into_error(result);
+                    error!("Failed to fetch {}: {}", url, e);
+                    return Err(e);
+                }
+                Outcome::Transient if attempt >= self.config.max_attempts => {
+                    self.stats.failed.fetch_add(1, Ordering::SeqCst);
+                    let e = into_error(result);
+                    error!("Giving up on {} after {} attempts: {}", url, attempt, e);
+                    return Err(e);
+                }
+                Outcome::Transient => {
+                    self.stats.retries.fetch_add(1, Ordering::SeqCst);
+                    let delay = backoff(&self.config, attempt);
+                    warn!(
+                        "Attempt {}/{} for {} failed: {}. Retrying in {:?}",
+                        attempt,
+                        self.config.max_attempts,
+                        url,
+                        into_error(result),
+                        delay
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+}
+
+impl Fetcher for RetryFetcher {
+    fn fetch<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<Page, FetchError>> {
+        Box::pin(self.fetch_with_retry(url))
+    }
+}
+
+pub fn classify(result: &Result<Page, FetchError>) -> Outcome {
+    match result {
+        Ok(page) if page.status < 400 && page.html.contains(&SELECTORS.labels.not_found) => {
+            Outcome::NotFound
+        }
+        Ok(page) => classify_status(page.status),
+        Err(FetchError::Http(e))
+            if e.is_timeout() || e.is_connect() || e.is_request() || e.is_body() =>
+        {
+            Outcome::Transient
+        }
+        Err(FetchError::Http(e)) => match e.status() {
+            Some(status) => classify_status(status.as_u16()),
+            None => Outcome::Permanent,
+        },
+        Err(FetchError::Status(status)) => classify_status(*status),
+        Err(FetchError::Blocked(_)) => Outcome::Transient,
+        Err(FetchError::Aborted(_)) | Err(FetchError::NotCached(_)) | Err(FetchError::Other(_)) => {
+            Outcome::Permanent
+        }
+    }
+}
+
+fn classify_status(status: u16) -> Outcome {
+    match status {
+        200..=399 => Outcome::Success,
+        404 | 410 => Outcome::NotFound,
+        408 | 429 | 500..=599 => Outcome::Transient,
+        _ => Outcome::Permanent,
+    }
+}
+
+/// Exponential delay for the given attempt, capped by the config. Half of it
+/// is randomized, so parallel fetchers do not retry in lockstep.
+pub fn backoff(config: &RetryConfig, attempt: u32) -> Duration {
+    let exponential =
+        config.initial_backoff_millis as f64 * config.multiplier.powi(attempt as i32 - 1);
+    let capped = exponential.min(config.max_backoff_millis as f64) as u64;
+    let half = capped / 2;
+    Duration::from_millis(half + rand::thread_rng().gen_range(0..=half))
+}
+
+fn into_error(result: Result<Page, FetchError>) -> FetchError {
+    match result {
+        Ok(page) => FetchError::Status(page.status),
+        Err(e) => e,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{atomic::Ordering, Arc};
+
+    use mockito::Matcher;
+
+    use crate::{
+        config::app_config::RetryConfig,
+        http::{
+            fetcher::{FetchError, Fetcher, Page},
+            mock::{fixture, MockFetcher},
+        },
+    };
+
+    use super::{backoff, classify, FetchStats, Outcome, RetryFetcher};
+
+    const DETAILS: &str = "https://www.mobile.bg/pcgi/mobile.cgi?act=4&adv=1001&slink=uafcc1";
+
+    fn page(status: u16, html: &str) -> Result<Page, FetchError> {
+        Ok(Page {
+            url: DETAILS.to_string(),
+            status,
+            html: html.to_string(),
+            ..Default::default()
+        })
+    }
+
+    fn fast_retry() -> RetryConfig {
+        RetryConfig {
+            max_attempts: 3,
+            initial_backoff_millis: 1,
+            max_backoff_millis: 5,
+            multiplier: 2.0,
+        }
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(Outcome::Success, classify(&page(200, "<html></html>")));
+        assert_eq!(
+            Outcome::NotFound,
+            classify(&page(200, "Обявата е изтрита или не е активна"))
+        );
+        assert_eq!(Outcome::NotFound, classify(&page(404, "")));
+        assert_eq!(Outcome::Transient, classify(&page(429, "")));
+        assert_eq!(Outcome::Transient, classify(&page(503, "")));
+        assert_eq!(Outcome::Permanent, classify(&page(403, "")));
+        assert_eq!(Outcome::Transient, classify(&Err(FetchError::Status(502))));
+        assert_eq!(
+            Outcome::Permanent,
+            classify(&Err(FetchError::Other("invalid url".to_string())))
+        );
+    }
+
+    #[test]
+    fn test_backoff() {
+        let config = RetryConfig {
+            max_attempts: 5,
+            initial_backoff_millis: 100,
+            max_backoff_millis: 300,
+            multiplier: 2.0,
+        };
+        let first = backoff(&config, 1).as_millis();
+        assert!((50..=100).contains(&first));
+        let second = backoff(&config, 2).as_millis();
+        assert!((100..=200).contains(&second));
+        let capped = backoff(&config, 4).as_millis();
+        assert!((150..=300).contains(&capped));
+    }
+
+    #[tokio::test]
+    async fn test_retry_transient_errors() {
+        let mut mock = MockFetcher::new().await;
+        let query = Matcher::UrlEncoded("adv".into(), "1001".into());
+        mock.mock_failures(query, 503, 2).await;
+        mock.add_fixtures().await;
+        let stats = Arc::new(FetchStats::default());
+        let fetcher = RetryFetcher::new(Arc::new(mock), fast_retry(), stats.clone());
+        let page = fetcher.fetch(DETAILS).await.unwrap();
+        assert_eq!(200, page.status);
+        assert_eq!(fixture("details.html").trim(), page.html.trim());
+        assert_eq!(3, stats.attempts.load(Ordering::SeqCst));
+        assert_eq!(2, stats.retries.load(Ordering::SeqCst));
+        assert_eq!(1, stats.succeeded.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_retry_gives_up() {
+        let mut mock = MockFetcher::new().await;
+        let query = Matcher::UrlEncoded("adv".into(), "1001".into());
+        mock.mock_failures(query, 500, 5).await;
+        mock.add_fixtures().await;
+        let stats = Arc::new(FetchStats::default());
+        let fetcher = RetryFetcher::new(Arc::new(mock), fast_retry(), stats.clone());
+        let result = fetcher.fetch(DETAILS).await;
+        assert!(matches!(result, Err(FetchError::Status(500))));
+        assert_eq!(3, stats.attempts.load(Ordering::SeqCst));
+        assert_eq!(1, stats.failed.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_not_found_is_not_retried() {
+        let mut mock = MockFetcher::with_fixtures().await;
+        mock.mock_details(Some("1001"), &fixture("not_found.html"))
+            .await;
+        let stats = Arc::new(FetchStats::default());
+        let fetcher = RetryFetcher::new(Arc::new(mock), fast_retry(), stats.clone());
+        let page = fetcher.fetch(DETAILS).await.unwrap();
+        assert_eq!(200, page.status);
+        assert_eq!(1, stats.attempts.load(Ordering::SeqCst));
+        assert_eq!(1, stats.not_found.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,355 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use log::{error, info, warn};
+use regex::Regex;
+use reqwest::Url;
+
+use super::{client::normalize_url, fetcher::Fetcher};
+
+pub const ROBOTS_PATH: &str = "/robots.txt";
+
+#[derive(Debug, Clone)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+    regex: Regex,
+}
+
+impl Rule {
+    /// `*` matches any sequence of characters and a trailing `$` anchors the
+    /// pattern to the end of the path.
+    fn new(allow: bool, pattern: &str) -> Option<Self> {
+        let (body, anchored) = match pattern.strip_suffix('$') {
+            Some(body) => (body, true),
+            None => (pattern, false),
+        };
+        let escaped = body
+            .split('*')
+            .map(regex::escape)
+            .collect::<Vec<String>>()
+            .join(".*");
+        let regex = format!("^{}{}", escaped, if anchored { "$" } else { "" });
+        Some(Rule {
+            allow,
+            pattern: pattern.to_string(),
+            regex: Regex::new(&regex).ok()?,
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct Group {
+    agents: Vec<String>,
+    rules: Vec<Rule>,
+    crawl_delay: Option<f64>,
+}
+
+/// The rules of a robots.txt that apply to one user agent.
+#[derive(Debug, Clone, Default)]
+pub struct RobotsRules {
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+}
+
+impl RobotsRules {
+    pub fn allow_all() -> Self {
+        RobotsRules::default()
+    }
+
+    pub fn disallow_all() -> Self {
+        RobotsRules {
+            rules: Rule::new(false, "/").into_iter().collect(),
+            crawl_delay: None,
+        }
+    }
+
+    /// Takes the groups naming `user_agent` or, when there are none, the `*`
+    /// groups. Unknown lines such as `Sitemap` are ignored.
+    pub fn parse(content: &str, user_agent: &str) -> Self {
+        let mut groups: Vec<Group> = vec![];
+        let mut reading_agents = false;
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim().to_lowercase().as_str() {
+                "user-agent" => {
+                    if !reading_agents {
+                        groups.push(Group::default());
+                    }
+                    reading_agents = true;
+                    if let Some(group) = groups.last_mut() {
+                        group.agents.push(value.to_lowercase());
+                    }
+                }
+                key @ ("allow" | "disallow") => {
+                    reading_agents = false;
+                    // An empty disallow allows everything.
+                    if value.is_empty() {
+                        continue;
+                    }
+                    if let Some(group) = groups.last_mut() {
+                        group.rules.extend(Rule::new(key == "allow", value));
+                    }
+                }
+                "crawl-delay" => {
+                    reading_agents = false;
+                    if let Some(group) = groups.last_mut() {
+                        group.crawl_delay = value.parse::<f64>().ok().filter(|d| *d >= 0.0);
+                    }
+                }
+                _ => reading_agents = false,
+            }
+        }
+
+        let user_agent = user_agent.to_lowercase();
+        let own = groups
+            .iter()
+            .filter(|g| g.agents.iter().any(|a| a != "*" && user_agent.contains(a)))
+            .collect::<Vec<&Group>>();
+        let selected = if own.is_empty() {
+            groups
+                .iter()
+                .filter(|g| g.agents.iter().any(|a| a == "*"))
+                .collect::<Vec<&Group>>()
+        } else {
+            own
+        };
+        RobotsRules {
+            rules: selected
+                .iter()
+                .flat_map(|g| g.rules.iter().cloned())
+                .collect(),
+            crawl_delay: selected
+                .iter()
+                .find_map(|g| g.crawl_delay)
+                .map(Duration::from_secs_f64),
+        }
+    }
+
+    /// The longest matching rule wins, an allow rule wins a tie.
+    pub fn is_allowed(&self, path: &str) -> bool {
+        if path == ROBOTS_PATH {
+            return true;
+        }
+        self.rules
+            .iter()
+            .filter(|rule| rule.regex.is_match(path))
+            .max_by_key(|rule| (rule.pattern.len(), rule.allow))
+            .map(|rule| rule.allow)
+            .unwrap_or(true)
+    }
+
+    pub fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+    }
+}
+
+/// robots.txt of every host, fetched the first time a url of the host is
+/// checked.
+///
+/// A missing robots.txt (4xx) allows everything. One that could not be
+/// fetched at all disallows everything, as there is no way to tell what is
+/// allowed.
+pub struct RobotsCache {
+    fetcher: Option<Arc<dyn Fetcher>>,
+    user_agent: String,
+    hosts: tokio::sync::Mutex<HashMap<String, Arc<RobotsRules>>>,
+    skipped: Mutex<Vec<String>>,
+}
+
+impl RobotsCache {
+    pub fn new(fetcher: Arc<dyn Fetcher>, user_agent: &str) -> Self {
+        RobotsCache {
+            fetcher: Some(fetcher),
+            user_agent: user_agent.to_string(),
+            hosts: tokio::sync::Mutex::new(HashMap::new()),
+            skipped: Mutex::new(vec![]),
+        }
+    }
+
+    /// Allows every url without fetching anything.
+    pub fn disabled() -> Self {
+        RobotsCache {
+            fetcher: None,
+            user_agent: String::new(),
+            hosts: tokio::sync::Mutex::new(HashMap::new()),
+            skipped: Mutex::new(vec![]),
+        }
+    }
+
+    pub async fn rules(&self, url: &str) -> Arc<RobotsRules> {
+        let Some(fetcher) = &self.fetcher else {
+            return Arc::new(RobotsRules::allow_all());
+        };
+        let Some(robots_url) = robots_url(url) else {
+            return Arc::new(RobotsRules::allow_all());
+        };
+        // Held while fetching, so every host is fetched once.
+        let mut hosts = self.hosts.lock().await;
+        if let Some(rules) = hosts.get(&robots_url) {
+            return rules.clone();
+        }
+        let rules = match fetcher.fetch(&robots_url).await {
+            Ok(page) if page.status < 400 => {
+                info!("Loaded {}", robots_url);
+                RobotsRules::parse(&page.html, &self.user_agent)
+            }
+            Ok(page) if page.status < 500 => {
+                warn!("No {} ({}), everything is allowed", robots_url, page.status);
+                RobotsRules::allow_all()
+            }
+            Ok(page) => {
+                error!("Failed to fetch {}: {}", robots_url, page.status);
+                RobotsRules::disallow_all()
+            }
+            Err(e) => {
+                error!("Failed to fetch {}: {}", robots_url, e);
+                RobotsRules::disallow_all()
+            }
+        };
+        let rules = Arc::new(rules);
+        hosts.insert(robots_url, rules.clone());
+        rules
+    }
+
+    /// Checks the url and remembers it when it is disallowed.
+    pub async fn is_allowed(&self, url: &str) -> bool {
+        let allowed = self.rules(url).await.is_allowed(&path_of(url));
+        if !allowed {
+            info!("Skipping {}, disallowed by robots.txt", url);
+            self.skipped.lock().unwrap().push(url.to_string());
+        }
+        allowed
+    }
+
+    pub async fn crawl_delay(&self, url: &str) -> Option<Duration> {
+        self.rules(url).await.crawl_delay()
+    }
+
+    /// The urls disallowed so far.
+    pub fn skipped(&self) -> Vec<String> {
+        self.skipped.lock().unwrap().clone()
+    }
+}
+
+fn robots_url(url: &str) -> Option<String> {
+    let url = Url::parse(&normalize_url(url)).ok()?;
+    let host = url.host_str()?;
+    Some(match url.port() {
+        Some(port) => format!("{}://{}:{}{}", url.scheme(), host, port, ROBOTS_PATH),
+        None => format!("{}://{}{}", url.scheme(), host, ROBOTS_PATH),
+    })
+}
+
+/// The path and the query the rules are matched against.
+fn path_of(url: &str) -> String {
+    match Url::parse(&normalize_url(url)) {
+        Ok(url) => match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        },
+        Err(_) => url.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use crate::{
+        http::mock::{robots_fixture, MockFetcher},
+        utils::helpers::read_file_from,
+    };
+
+    use super::{path_of, robots_url, RobotsCache, RobotsRules};
+
+    fn parse_fixture(file_name: &str, user_agent: &str) -> RobotsRules {
+        let content = read_file_from("resources/test-data/robots", file_name).unwrap();
+        RobotsRules::parse(&content, user_agent)
+    }
+
+    #[test]
+    fn test_own_group() {
+        let rules = parse_fixture("groups.txt", "data-scraper");
+        assert!(rules.is_allowed("/pcgi/mobile.cgi?act=3&f1=1"));
+        assert!(!rules.is_allowed("/pcgi/mobile.cgi?act=5&adv=1"));
+        assert!(!rules.is_allowed("/pcgi/other.cgi"));
+        assert!(!rules.is_allowed("/files/price.pdf"));
+        assert!(rules.is_allowed("/files/price.pdf.html"));
+        assert!(rules.is_allowed("/private/"));
+        assert_eq!(Some(Duration::from_millis(2500)), rules.crawl_delay());
+    }
+
+    #[test]
+    fn test_default_group() {
+        let rules = parse_fixture("groups.txt", "another-scraper");
+        assert!(!rules.is_allowed("/private/data"));
+        assert!(rules.is_allowed("/pcgi/mobile.cgi?act=5"));
+        assert_eq!(Some(Duration::from_secs(10)), rules.crawl_delay());
+
+        let rules = parse_fixture("disallow_all.txt", "data-scraper");
+        assert!(!rules.is_allowed("/pcgi/mobile.cgi?act=3"));
+        assert!(rules.is_allowed("/robots.txt"));
+        assert!(RobotsRules::parse("", "data-scraper").is_allowed("/"));
+    }
+
+    #[test]
+    fn test_urls() {
+        assert_eq!(
+            Some("https://www.mobile.bg/robots.txt".to_string()),
+            robots_url("//www.mobile.bg/pcgi/mobile.cgi?act=3")
+        );
+        assert_eq!(
+            "/pcgi/mobile.cgi?act=3&f1=1",
+            path_of("//www.mobile.bg/pcgi/mobile.cgi?act=3&f1=1")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_robots_cache() {
+        let mut fetcher = MockFetcher::new().await;
+        fetcher
+            .mock_robots(200, &robots_fixture("mobile.bg.txt"))
+            .await;
+        let robots = RobotsCache::new(Arc::new(fetcher), "data-scraper");
+        assert!(
+            robots
+                .is_allowed("//www.mobile.bg/pcgi/mobile.cgi?act=3&f1=1")
+                .await
+        );
+        assert!(
+            !robots
+                .is_allowed("//www.mobile.bg/pcgi/mobile.cgi?act=5&adv=1")
+                .await
+        );
+        assert_eq!(
+            vec!["//www.mobile.bg/pcgi/mobile.cgi?act=5&adv=1".to_string()],
+            robots.skipped()
+        );
+
+        let mut fetcher = MockFetcher::new().await;
+        fetcher.mock_robots(503, "").await;
+        let robots = RobotsCache::new(Arc::new(fetcher), "data-scraper");
+        assert!(
+            !robots
+                .is_allowed("//www.mobile.bg/pcgi/mobile.cgi?act=3")
+                .await
+        );
+
+        let mut fetcher = MockFetcher::new().await;
+        fetcher.mock_robots(404, "").await;
+        let robots = RobotsCache::new(Arc::new(fetcher), "data-scraper");
+        assert!(
+            robots
+                .is_allowed("//www.mobile.bg/pcgi/mobile.cgi?act=5")
+                .await
+        );
+    }
+}
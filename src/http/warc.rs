@@ -0,0 +1,372 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use flate2::{read::MultiGzDecoder, write::GzEncoder, Compression};
+use futures::future::BoxFuture;
+use log::{info, warn};
+
+use crate::DATE_FORMAT;
+
+use super::{
+    cache::cache_key,
+    charset,
+    fetcher::{FetchError, Fetcher, Page},
+};
+
+const WARC_VERSION: &str = "WARC/1.1";
+const WARC_EXTENSION: &str = ".warc.gz";
+
+/// A raw response, as it was received from the site.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchivedResponse {
+    pub url: String,
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    pub fetched_at: DateTime<Utc>,
+}
+
+impl ArchivedResponse {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        header(&self.headers, name)
+    }
+
+    /// Decodes the body the same way the live fetcher does.
+    pub fn to_page(&self) -> Page {
+        let (html, encoding) = charset::decode(self.header("content-type"), &self.body);
+        Page {
+            url: self.url.clone(),
+            status: self.status,
+            html,
+            encoding: encoding.to_string(),
+        }
+    }
+}
+
+/// Appends the responses to a `pages-<date>.warc.gz` file per day.
+///
+/// Every record is a gzip member of its own, so a file cut short by a crash
+/// loses only the record that was being written.
+#[derive(Debug)]
+pub struct WarcWriter {
+    dir: PathBuf,
+    current: Mutex<Option<(NaiveDate, File)>>,
+}
+
+impl WarcWriter {
+    pub fn new(dir: &str) -> Self {
+        WarcWriter {
+            dir: PathBuf::from(dir),
+            current: Mutex::new(None),
+        }
+    }
+
+    pub fn file_for(&self, date: NaiveDate) -> PathBuf {
+        self.dir.join(format!(
+            "pages-{}{}",
+            date.format(DATE_FORMAT),
+            WARC_EXTENSION
+        ))
+    }
+
+    pub fn write(&self, response: &ArchivedResponse) -> io::Result<()> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&to_record(response))?;
+        let member = encoder.finish()?;
+
+        let date = response.fetched_at.date_naive();
+        let mut current = self.current.lock().unwrap();
+        if current.as_ref().map(|(day, _)| *day) != Some(date) {
+            fs::create_dir_all(&self.dir)?;
+            let path = self.file_for(date);
+            info!("Archiving the responses to {:?}", path);
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            *current = Some((date, file));
+        }
+        match current.as_mut() {
+            Some((_, file)) => file.write_all(&member),
+            None => Ok(()),
+        }
+    }
+}
+
+fn to_record(response: &ArchivedResponse) -> Vec<u8> {
+    let reason = reqwest::StatusCode::from_u16(response.status)
+        .ok()
+        .and_then(|status| status.canonical_reason())
+        .unwrap_or("");
+    let mut block = format!("HTTP/1.1 {} {}\r\n", response.status, reason).into_bytes();
+    for (name, value) in &response.headers {
+        block.extend(format!("{}: {}\r\n", name, value).into_bytes());
+    }
+    block.extend(b"\r\n");
+    block.extend(&response.body);
+
+    let mut record = format!(
+        "{}\r\nWARC-Type: response\r\nWARC-Record-ID: <urn:uuid:{}>\r\nWARC-Date: {}\r\n\
+         WARC-Target-URI: {}\r\nContent-Type: application/http; msgtype=response\r\n\
+         Content-Length: {}\r\n\r\n",
+        WARC_VERSION,
+        record_id(),
+        response
+            .fetched_at
+            .to_rfc3339_opts(SecondsFormat::Secs, true),
+        response.url,
+        block.len()
+    )
+    .into_bytes();
+    record.extend(block);
+    record.extend(b"\r\n\r\n");
+    record
+}
+
+/// Random (version 4) uuid.
+fn record_id() -> String {
+    let mut bytes: [u8; 16] = rand::random();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = bytes
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Reads the response records of a `.warc.gz` file. Other record types are
+/// skipped.
+pub fn read_archive(path: &Path) -> io::Result<Vec<ArchivedResponse>> {
+    let mut reader = BufReader::new(MultiGzDecoder::new(File::open(path)?));
+    let mut responses = vec![];
+    while let Some(headers) = read_warc_headers(&mut reader)? {
+        let length = header(&headers, "Content-Length")
+            .and_then(|value| value.parse::<usize>().ok())
+            .ok_or_else(|| invalid("WARC record without Content-Length".to_string()))?;
+        let mut block = vec![0; length];
+        reader.read_exact(&mut block)?;
+        if header(&headers, "WARC-Type") != Some("response") {
+            continue;
+        }
+        let url = header(&headers, "WARC-Target-URI").unwrap_or_default();
+        let fetched_at = header(&headers, "WARC-Date")
+            .and_then(|date| DateTime::parse_from_rfc3339(date).ok())
+            .map(|date| date.with_timezone(&Utc))
+            .unwrap_or_default();
+        responses.push(parse_http(url, fetched_at, block)?);
+    }
+    Ok(responses)
+}
+
+/// The named fields of the next record, `None` at the end of the file.
+fn read_warc_headers(reader: &mut impl BufRead) -> io::Result<Option<Vec<(String, String)>>> {
+    let mut line = String::new();
+    // The blank lines closing the previous record.
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        if !line.trim().is_empty() {
+            break;
+        }
+    }
+    if !line.starts_with("WARC/") {
+        return Err(invalid(format!("Not a WARC record: {}", line.trim())));
+    }
+    let mut headers = vec![];
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            return Ok(Some(headers));
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+}
+
+fn parse_http(
+    url: &str,
+    fetched_at: DateTime<Utc>,
+    block: Vec<u8>,
+) -> io::Result<ArchivedResponse> {
+    let end = block
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or_else(|| invalid(format!("No http headers in the record of {}", url)))?;
+    let head = String::from_utf8_lossy(&block[..end]).to_string();
+    let mut lines = head.split("\r\n");
+    let status = lines
+        .next()
+        .and_then(|status_line| status_line.split_whitespace().nth(1))
+        .and_then(|status| status.parse::<u16>().ok())
+        .ok_or_else(|| invalid(format!("No http status in the record of {}", url)))?;
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect();
+    Ok(ArchivedResponse {
+        url: url.to_string(),
+        status,
+        headers,
+        body: block[end + 4..].to_vec(),
+        fetched_at,
+    })
+}
+
+/// Serves the archived pages to the parsers without network access. When a
+/// url was archived more than once the latest response wins.
+#[derive(Debug, Default)]
+pub struct ArchiveFetcher {
+    pages: HashMap<String, Page>,
+}
+
+impl ArchiveFetcher {
+    pub fn from_files(paths: &[PathBuf]) -> io::Result<Self> {
+        let mut responses = vec![];
+        for path in paths {
+            responses.extend(read_archive(path)?);
+        }
+        responses.sort_by_key(|response| response.fetched_at);
+        let pages = responses
+            .iter()
+            .map(|response| (cache_key(&response.url), response.to_page()))
+            .collect::<HashMap<String, Page>>();
+        info!("Loaded {} archived pages", pages.len());
+        Ok(ArchiveFetcher { pages })
+    }
+
+    /// Loads every `.warc.gz` file of the directory.
+    pub fn from_dir(dir: &str) -> io::Result<Self> {
+        let mut paths = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.to_string_lossy().ends_with(WARC_EXTENSION))
+            .collect::<Vec<PathBuf>>();
+        paths.sort();
+        if paths.is_empty() {
+            warn!("No archives in {}", dir);
+        }
+        ArchiveFetcher::from_files(&paths)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+}
+
+impl Fetcher for ArchiveFetcher {
+    fn fetch<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<Page, FetchError>> {
+        Box::pin(async move {
+            match self.pages.get(&cache_key(url)) {
+                Some(page) => Ok(Page {
+                    url: url.to_string(),
+                    ..page.clone()
+                }),
+                None => Err(FetchError::Other(format!("not in the archive: {}", url))),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+    use encoding_rs::WINDOWS_1251;
+
+    use crate::{
+        http::{fetcher::Fetcher, mock::fixture},
+        scraper::mobile_bg::{get_details, get_links},
+    };
+
+    use super::{read_archive, ArchiveFetcher, ArchivedResponse, WarcWriter};
+
+    const DETAILS: &str = "https://www.mobile.bg/pcgi/mobile.cgi?act=4&adv=1001&slink=uafcc1";
+    const LISTING: &str = "https://www.mobile.bg/pcgi/mobile.cgi?act=3&slink=uafcc1&f1=1";
+
+    fn response(url: &str, html: &str, days: i64) -> ArchivedResponse {
+        ArchivedResponse {
+            url: url.to_string(),
+            status: 200,
+            headers: vec![(
+                "content-type".to_string(),
+                "text/html; charset=windows-1251".to_string(),
+            )],
+            body: WINDOWS_1251.encode(html).0.to_vec(),
+            fetched_at: Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap() + Duration::days(days),
+        }
+    }
+
+    fn warc_dir(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("data-scraper-warc-{}", name));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_write_and_read() {
+        let dir = warc_dir("roundtrip");
+        let writer = WarcWriter::new(&dir);
+        let details = response(DETAILS, &fixture("details.html"), 0);
+        let listing = response(LISTING, &fixture("listing.html"), 0);
+        let next_day = response(DETAILS, &fixture("not_found.html"), 1);
+        for r in [&details, &listing, &next_day] {
+            writer.write(r).unwrap();
+        }
+
+        let first = read_archive(&writer.file_for(details.fetched_at.date_naive())).unwrap();
+        assert_eq!(vec![details, listing], first);
+        let second = read_archive(&writer.file_for(next_day.fetched_at.date_naive())).unwrap();
+        assert_eq!(vec![next_day], second);
+    }
+
+    #[tokio::test]
+    async fn test_parse_archived_pages() {
+        let dir = warc_dir("parse");
+        let writer = WarcWriter::new(&dir);
+        writer
+            .write(&response(DETAILS, &fixture("details.html"), 0))
+            .unwrap();
+        writer
+            .write(&response(LISTING, &fixture("listing.html"), 0))
+            .unwrap();
+
+        let fetcher = ArchiveFetcher::from_dir(&dir).unwrap();
+        assert_eq!(2, fetcher.len());
+        let details = get_details(&fetcher, DETAILS).await.unwrap();
+        assert_eq!("Audi", details.make);
+        assert_eq!("1001", details.id);
+        let links = get_links(&fetcher, LISTING).await;
+        assert_eq!(2, links.len());
+        assert!(fetcher
+            .fetch("https://www.mobile.bg/pcgi/mobile.cgi?act=4&adv=2")
+            .await
+            .is_err());
+    }
+}
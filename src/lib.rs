@@ -1,4 +1,5 @@
 pub mod config;
+pub mod http;
 pub mod model;
 pub mod scraper;
 pub mod services;
@@ -23,6 +24,14 @@ pub const GEARBOX_TXT: &str = "Скоростна кутия";
 pub const ENGINE_TXT: &str = "Тип двигател";
 pub const MILLAGE_TXT: &str = "Пробег";
 pub const YEAR_TXT: &str = "Дата на производство";
+pub const CATEGORY_TXT: &str = "Категория";
+pub const COLOUR_TXT: &str = "Цвят";
+pub const EURO_STANDARD_TXT: &str = "Евростандарт";
+pub const DISPLACEMENT_TXT: &str = "Кубатура";
+pub const DOORS_TXT: &str = "Брой врати";
+pub const VIN_TXT: &str = "VIN";
+pub const PUBLISHED_TXT: &str = "Публикувана";
+pub const EDITED_TXT: &str = "Коригирана";
 
 pub const BROWSER_USER_AGENT: &str ="Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/15.1 Safari/605.1.15";
 
@@ -39,6 +48,11 @@ lazy_static! {
         CONFIG.get_data_dir(),
         CREATED_ON.clone()
     );
+    pub static ref LISTING_FILE_NAME: String = format!(
+        "{}/listing-{}.csv",
+        CONFIG.get_data_dir(),
+        CREATED_ON.clone()
+    );
     pub static ref UPDATED_VEHICLES_FILE_NAME: String = format!(
         "{}/updated_vehicle-{}.csv",
         CONFIG.get_data_dir(),
@@ -49,4 +63,22 @@ lazy_static! {
         format!("{}/for_update.csv", CONFIG.get_data_dir());
     pub static ref UPDATED_FILE_NAME: String = format!("{}/updated.csv", CONFIG.get_data_dir());
     pub static ref DELETED_FILE_NAME: String = format!("{}/deleted.csv", CONFIG.get_data_dir());
+    pub static ref REVIEW_FILE_NAME: String = format!(
+        "{}/review-{}.csv",
+        CONFIG.get_data_dir(),
+        CREATED_ON.clone()
+    );
+    pub static ref DESCRIPTION_FILE_NAME: String = format!(
+        "{}/description-{}.csv",
+        CONFIG.get_data_dir(),
+        CREATED_ON.clone()
+    );
+    pub static ref IMAGES_DIR: String = format!("{}/images", CONFIG.get_data_dir());
+    pub static ref CACHE_DIR: String = format!("{}/cache", CONFIG.get_data_dir());
+    pub static ref WARC_DIR: String = format!("{}/warc", CONFIG.get_data_dir());
+    pub static ref ERRORS_FILE_NAME: String = format!(
+        "{}/errors_{}.csv",
+        CONFIG.get_data_dir(),
+        CREATED_ON.clone()
+    );
 }
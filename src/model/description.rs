@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+use crate::CREATED_ON;
+
+use super::traits::{Header, Identity};
+
+/// The free text of an advert. Kept out of the vehicle files, which stay
+/// one short line per advert.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Description {
+    pub id: String,
+    pub text: String,
+    pub created_on: String,
+}
+
+impl Description {
+    pub fn new(id: &str, text: &str) -> Self {
+        Description {
+            id: id.to_string(),
+            text: text.to_string(),
+            created_on: CREATED_ON.to_string(),
+        }
+    }
+}
+
+impl Header for Description {
+    fn header() -> Vec<&'static str> {
+        vec!["id", "text", "created_on"]
+    }
+}
+
+impl Identity for Description {
+    fn get_id(&self) -> String {
+        self.id.clone()
+    }
+}
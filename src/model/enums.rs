@@ -1,8 +1,8 @@
-use std::str::FromStr;
+use std::{fmt, str::FromStr};
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize, Default)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize, Default)]
 pub enum Currency {
     #[default]
     #[serde(rename = "BGN")]
@@ -74,26 +74,26 @@ pub enum Payload<T> {
     Done,
 }
 
-impl ToString for Gearbox {
-    fn to_string(&self) -> String {
+impl fmt::Display for Gearbox {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Gearbox::Automatic => "Автоматична".to_string(),
-            Gearbox::Manual => "Ръчна".to_string(),
-            Gearbox::Semiautomatic => "Полуавтоматична".to_string(),
-            Gearbox::NotAvailable => "NotFound".to_string(),
+            Gearbox::Automatic => write!(f, "Автоматична"),
+            Gearbox::Manual => write!(f, "Ръчна"),
+            Gearbox::Semiautomatic => write!(f, "Полуавтоматична"),
+            Gearbox::NotAvailable => write!(f, "NotFound"),
         }
     }
 }
 
-impl ToString for Engine {
-    fn to_string(&self) -> String {
+impl fmt::Display for Engine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Engine::Petrol => "Бензинов".to_string(),
-            Engine::Diesel => "Дизелов".to_string(),
-            Engine::PluginHybrid => "Plug-in хибрид".to_string(),
-            Engine::Electric => "Електрически".to_string(),
-            Engine::Hybrid => "Хибриден".to_string(),
-            Engine::NotAvailable => "NotFound".to_string(),
+            Engine::Petrol => write!(f, "Бензинов"),
+            Engine::Diesel => write!(f, "Дизелов"),
+            Engine::PluginHybrid => write!(f, "Plug-in хибрид"),
+            Engine::Electric => write!(f, "Електрически"),
+            Engine::Hybrid => write!(f, "Хибриден"),
+            Engine::NotAvailable => write!(f, "NotFound"),
         }
     }
 }
@@ -126,12 +126,37 @@ impl FromStr for Engine {
     }
 }
 
-impl ToString for Currency {
-    fn to_string(&self) -> String {
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Currency::BGN => "BGN".to_string(),
-            Currency::EUR => "EUR".to_string(),
-            Currency::USD => "USD".to_string(),
+            Currency::BGN => write!(f, "BGN"),
+            Currency::EUR => write!(f, "EUR"),
+            Currency::USD => write!(f, "USD"),
+        }
+    }
+}
+
+impl fmt::Display for Dealer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Dealer::PRIVATE => write!(f, "Private"),
+            Dealer::DEALER => write!(f, "Dealer"),
+            Dealer::ALL => write!(f, "All"),
+        }
+    }
+}
+
+/// Reads the values written by `Serialize`. The `true` and `false` of the old
+/// CSV files were inverted: `true` is a private seller.
+impl FromStr for Dealer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Private" | "true" => Ok(Dealer::PRIVATE),
+            "Dealer" | "false" => Ok(Dealer::DEALER),
+            "All" | "" => Ok(Dealer::ALL),
+            _ => Err(format!("Invalid dealer: {}", s)),
         }
     }
 }
@@ -149,12 +174,12 @@ impl FromStr for Currency {
     }
 }
 
-impl ToString for SaleType {
-    fn to_string(&self) -> String {
+impl fmt::Display for SaleType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            SaleType::INSALE => "INSALE".to_string(),
-            SaleType::SOLD => "SOLD".to_string(),
-            SaleType::NONE => "NONE".to_string(),
+            SaleType::INSALE => write!(f, "INSALE"),
+            SaleType::SOLD => write!(f, "SOLD"),
+            SaleType::NONE => write!(f, "NONE"),
         }
     }
 }
@@ -14,6 +14,16 @@ pub struct DataError {
     pub created_on: String,
 }
 
+impl DataError {
+    pub fn new(id: &str, error: &str) -> Self {
+        DataError {
+            id: id.to_string(),
+            error: error.to_string(),
+            created_on: Local::now().format(DATE_FORMAT).to_string(),
+        }
+    }
+}
+
 impl From<HashMap<String, String>> for DataError {
     fn from(map: HashMap<String, String>) -> Self {
         DataError {
@@ -1,6 +1,9 @@
+pub mod description;
 pub mod enums;
 pub mod error;
 pub mod id_list;
 pub mod records;
+pub mod review;
 pub mod search_metadata;
+pub mod spec;
 pub mod traits;
@@ -1,11 +1,18 @@
 use std::{collections::HashMap, str::FromStr};
 
-use serde::{Deserialize, Serialize};
+use chrono::{NaiveDate, NaiveDateTime};
+use log::warn;
+use serde::{Deserialize, Deserializer, Serialize};
 
-use crate::CREATED_ON;
+use crate::{
+    config::rates::{parse_date, ExchangeRates, RATES},
+    scraper::details::DetailsPage,
+    CREATED_ON,
+};
 
 use super::{
-    enums::{Currency, Engine, Gearbox},
+    enums::{Currency, Dealer, Engine, Gearbox},
+    spec::VehicleSpec,
     traits::{Header, Identity},
 };
 
@@ -28,7 +35,77 @@ pub struct MobileRecord {
     pub top: bool,
     pub vip: bool,
     pub sold: bool,
-    pub dealer: bool,
+    /// Also reads the inverted `true`/`false` of the old files.
+    #[serde(deserialize_with = "dealer_or_legacy_flag")]
+    pub dealer: Dealer,
+    #[serde(default)]
+    pub region: String,
+    #[serde(default)]
+    pub city: String,
+    #[serde(default)]
+    pub lat: Option<f64>,
+    #[serde(default)]
+    pub lon: Option<f64>,
+    #[serde(default)]
+    pub variant: Option<String>,
+    /// The advert title the make and model were read from.
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub price_negotiable: bool,
+    #[serde(default)]
+    pub price_on_request: bool,
+    /// Empty when the price says nothing about VAT.
+    #[serde(default)]
+    pub vat_included: Option<bool>,
+    /// The second amount of the dual BGN/EUR display.
+    #[serde(default)]
+    pub price_secondary: Option<u32>,
+    /// The currency of `price_secondary`, BGN when the price is in EUR.
+    #[serde(default, alias = "currency_secondary")]
+    pub price_secondary_currency: Option<Currency>,
+    /// The price converted with the rates of `created_on`.
+    #[serde(default)]
+    pub price_bgn: Option<u32>,
+    #[serde(default)]
+    pub price_eur: Option<u32>,
+    #[serde(default)]
+    pub category: String,
+    #[serde(default)]
+    pub colour: String,
+    #[serde(default)]
+    pub euro_standard: String,
+    #[serde(default)]
+    pub displacement: u16,
+    #[serde(default)]
+    pub doors: u8,
+    #[serde(default)]
+    pub vin: String,
+    /// The rest of the specification as a json object.
+    #[serde(default)]
+    pub extra_specs: String,
+    /// The month of `year`, the production year.
+    #[serde(default)]
+    pub production_month: Option<u8>,
+    #[serde(default)]
+    pub image_count: u16,
+    /// The photo urls as a json array. The description is saved aside.
+    #[serde(default)]
+    pub images: String,
+    #[serde(default)]
+    pub dealer_name: String,
+    #[serde(default)]
+    pub dealer_url: String,
+    #[serde(default)]
+    pub dealer_id: String,
+    #[serde(default)]
+    pub dealer_since: String,
+    /// When the advert was published and last edited, Sofia local time.
+    /// `created_on` is the day it was first scraped.
+    #[serde(default)]
+    pub published_at: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub edited_at: Option<NaiveDateTime>,
     pub created_on: String,
     pub updated_on: String,
     pub deleted_on: String,
@@ -55,6 +132,35 @@ impl Header for MobileRecord {
             "vip",
             "sold",
             "dealer",
+            "region",
+            "city",
+            "lat",
+            "lon",
+            "variant",
+            "title",
+            "price_negotiable",
+            "price_on_request",
+            "vat_included",
+            "price_secondary",
+            "price_secondary_currency",
+            "price_bgn",
+            "price_eur",
+            "category",
+            "colour",
+            "euro_standard",
+            "displacement",
+            "doors",
+            "vin",
+            "extra_specs",
+            "production_month",
+            "image_count",
+            "images",
+            "dealer_name",
+            "dealer_url",
+            "dealer_id",
+            "dealer_since",
+            "published_at",
+            "edited_at",
             "created_on",
             "updated_on",
             "deleted_on",
@@ -122,9 +228,8 @@ impl From<HashMap<String, String>> for MobileRecord {
             .unwrap();
         let dealer = map
             .get("dealer")
-            .unwrap_or(&"false".to_string())
-            .parse::<bool>()
-            .unwrap();
+            .and_then(|dealer| dealer.parse::<Dealer>().ok())
+            .unwrap_or_default();
         let sold = map
             .get("sold")
             .unwrap_or(&"false".to_string())
@@ -158,3 +263,190 @@ impl From<HashMap<String, String>> for MobileRecord {
         }
     }
 }
+
+impl From<DetailsPage> for MobileRecord {
+    fn from(details: DetailsPage) -> Self {
+        let mut record = MobileRecord {
+            id: details.id,
+            make: details.make,
+            model: details.model,
+            currency: details.price.currency,
+            price: details.price.amount,
+            price_negotiable: details.price.negotiable,
+            price_on_request: details.price.on_request,
+            vat_included: details.price.vat_included,
+            price_secondary: details.price.secondary.map(|(amount, _)| amount),
+            price_secondary_currency: details.price.secondary.map(|(_, currency)| currency),
+            millage: details.millage,
+            year: details.production_year,
+            production_month: details.production_month,
+            engine: details.engine,
+            gearbox: details.gearbox,
+            power: details.power,
+            phone: details.phone,
+            location: details.location,
+            view_count: details.view_count,
+            published_at: details.published_at,
+            edited_at: details.edited_at,
+            equipment: details.equipment,
+            top: details.top,
+            vip: details.vip,
+            sold: details.sold,
+            dealer: details.seller.dealer,
+            dealer_name: details.seller.name,
+            dealer_url: details.seller.profile_url,
+            dealer_id: details.seller.profile_id,
+            dealer_since: details.seller.registered_since,
+            region: details.place.region,
+            city: details.place.city,
+            lat: details.place.lat,
+            lon: details.place.lon,
+            variant: details.variant,
+            title: details.title,
+            extra_specs: details.spec.extra_as_json(),
+            image_count: details.images.len() as u16,
+            images: serde_json::to_string(&details.images).unwrap_or_default(),
+            category: details.spec.category,
+            colour: details.spec.colour,
+            euro_standard: details.spec.euro_standard,
+            displacement: details.spec.displacement,
+            doors: details.spec.doors,
+            vin: details.spec.vin,
+            created_on: CREATED_ON.to_string(),
+            ..Default::default()
+        };
+        record.normalize_price(&RATES);
+        if record.missing_rate() {
+            warn!(
+                "No exchange rate for {} on {}, advert {} has no normalized price",
+                record.currency, record.created_on, record.id
+            );
+        }
+        record
+    }
+}
+
+fn dealer_or_legacy_flag<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Dealer, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    value.parse::<Dealer>().map_err(serde::de::Error::custom)
+}
+
+impl MobileRecord {
+    /// Sets `price_bgn` and `price_eur` from `price`, with the rates of the
+    /// day the record was created.
+    pub fn normalize_price(&mut self, rates: &ExchangeRates) {
+        (self.price_bgn, self.price_eur) = match parse_date(&self.created_on) {
+            Some(date) => rates.normalize(self.price, self.currency, date),
+            None => (None, None),
+        };
+    }
+
+    /// A price that `normalize_price` could not convert for lack of a rate.
+    pub fn missing_rate(&self) -> bool {
+        self.price > 0 && (self.price_bgn.is_none() || self.price_eur.is_none())
+    }
+
+    /// Days from the publication of the advert to `until`, e.g. the day it
+    /// was deleted.
+    pub fn days_on_market(&self, until: NaiveDate) -> Option<i64> {
+        self.published_at
+            .map(|published_at| (until - published_at.date()).num_days())
+    }
+
+    pub fn image_urls(&self) -> Vec<String> {
+        serde_json::from_str(&self.images).unwrap_or_default()
+    }
+
+    pub fn spec(&self) -> VehicleSpec {
+        VehicleSpec {
+            category: self.category.clone(),
+            colour: self.colour.clone(),
+            euro_standard: self.euro_standard.clone(),
+            displacement: self.displacement,
+            doors: self.doors,
+            vin: self.vin.clone(),
+            extra: VehicleSpec::extra_from_json(&self.extra_specs),
+        }
+    }
+}
+
+/// One row of a listing page. Reads the search results only, so it is much
+/// cheaper than a `MobileRecord`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListingRecord {
+    pub id: String,
+    pub make: String,
+    pub model: String,
+    pub currency: Currency,
+    pub price: u32,
+    pub millage: u32,
+    pub year: u16,
+    pub promoted: bool,
+    /// The "капарирано" marker, a deposit was paid but the advert is still up.
+    #[serde(alias = "sold")]
+    pub reserved: bool,
+    pub created_on: String,
+}
+
+impl Header for ListingRecord {
+    fn header() -> Vec<&'static str> {
+        vec![
+            "id",
+            "make",
+            "model",
+            "currency",
+            "price",
+            "millage",
+            "year",
+            "promoted",
+            "reserved",
+            "created_on",
+        ]
+    }
+}
+
+impl Identity for ListingRecord {
+    fn get_id(&self) -> String {
+        self.id.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        model::enums::Currency,
+        scraper::{details::DetailsPage, price::parse_price},
+        services::file_processor::DataProcessor,
+        utils::helpers::create_empty_csv,
+        writer::persistance::{MobileData, MobileDataWriter},
+    };
+
+    use super::MobileRecord;
+
+    #[test]
+    fn test_secondary_price() {
+        let details = DetailsPage {
+            id: "1".to_string(),
+            price: parse_price("9 500 EUR 18 580.39 лв."),
+            ..Default::default()
+        };
+        let record = MobileRecord::from(details);
+        assert_eq!(Currency::EUR, record.currency);
+        assert_eq!(
+            (Some(18580), Some(Currency::BGN)),
+            (record.price_secondary, record.price_secondary_currency)
+        );
+
+        let file_name = std::env::temp_dir().join("data-scraper-secondary.csv");
+        let file_name = file_name.to_str().unwrap();
+        create_empty_csv::<MobileRecord>(file_name).unwrap();
+        MobileData::Payload(vec![record])
+            .write_csv(file_name, false)
+            .unwrap();
+        let records = DataProcessor::<MobileRecord>::from_files(vec![file_name]).get_values();
+        assert_eq!(Some(Currency::BGN), records[0].price_secondary_currency);
+        fs::remove_file(file_name).unwrap();
+    }
+}
@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+
+use crate::CREATED_ON;
+
+use super::traits::{Header, Identity};
+
+/// A value read from an advert that is missing from one of the bundled
+/// catalogues, `config/makes.yml` or `config/gazetteer.csv`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Review {
+    pub id: String,
+    /// `title` or `location`.
+    pub field: String,
+    pub value: String,
+    /// What was used instead.
+    pub guess: String,
+    pub created_on: String,
+}
+
+impl Review {
+    pub fn new(id: &str, field: &str, value: &str, guess: &str) -> Self {
+        Review {
+            id: id.to_string(),
+            field: field.to_string(),
+            value: value.to_string(),
+            guess: guess.to_string(),
+            created_on: CREATED_ON.to_string(),
+        }
+    }
+}
+
+impl Header for Review {
+    fn header() -> Vec<&'static str> {
+        vec!["id", "field", "value", "guess", "created_on"]
+    }
+}
+
+impl Identity for Review {
+    fn get_id(&self) -> String {
+        self.id.clone()
+    }
+}
@@ -1,9 +1,13 @@
-use log::info;
+use log::{error, info};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    scraper::mobile_bg::{get_header_data, get_pages, get_pages_async, slink},
-    utils::helpers::{extract_ascii_latin, mobile_search_url},
+    http::fetcher::Fetcher,
+    scraper::{
+        mobile_bg::{get_header_data, get_pages, slink},
+        summary::parse_summary,
+    },
+    utils::helpers::mobile_search_url,
     LISTING_URL, TIMESTAMP,
 };
 
@@ -41,21 +45,21 @@ impl Header for SearchMetadata {
     }
 }
 
-pub async fn astatistic() -> Vec<SearchMetadata> {
-    let insale = asearch(SaleType::INSALE, 0, 0).await;
-    let sold = asearch(SaleType::SOLD, 0, 0).await;
-    let all = asearch(SaleType::NONE, 0, 0).await;
+pub async fn astatistic(fetcher: &dyn Fetcher) -> Vec<SearchMetadata> {
+    let insale = asearch(fetcher, SaleType::INSALE, 0, 0).await;
+    let sold = asearch(fetcher, SaleType::SOLD, 0, 0).await;
+    let all = asearch(fetcher, SaleType::NONE, 0, 0).await;
     vec![all, insale, sold]
 }
 
-pub async fn asearches() -> Vec<SearchMetadata> {
-    let sold = asearch(SaleType::SOLD, 0, 0).await;
-    let insale_5000 = asearch(SaleType::INSALE, 1_001, 5000).await;
-    let insale_10_000 = asearch(SaleType::INSALE, 5001, 10_000).await;
-    let insale_15_000 = asearch(SaleType::INSALE, 10_001, 15_000).await;
-    let insale_20_000 = asearch(SaleType::INSALE, 15_001, 20_000).await;
-    let insale_30_000 = asearch(SaleType::INSALE, 20_001, 30_000).await;
-    let insale_30_000_up = asearch(SaleType::INSALE, 30_001, 0).await;
+pub async fn asearches(fetcher: &dyn Fetcher) -> Vec<SearchMetadata> {
+    let sold = asearch(fetcher, SaleType::SOLD, 0, 0).await;
+    let insale_5000 = asearch(fetcher, SaleType::INSALE, 1_001, 5000).await;
+    let insale_10_000 = asearch(fetcher, SaleType::INSALE, 5001, 10_000).await;
+    let insale_15_000 = asearch(fetcher, SaleType::INSALE, 10_001, 15_000).await;
+    let insale_20_000 = asearch(fetcher, SaleType::INSALE, 15_001, 20_000).await;
+    let insale_30_000 = asearch(fetcher, SaleType::INSALE, 20_001, 30_000).await;
+    let insale_30_000_up = asearch(fetcher, SaleType::INSALE, 30_001, 0).await;
     vec![
         sold,
         insale_5000,
@@ -67,32 +71,21 @@ pub async fn asearches() -> Vec<SearchMetadata> {
     ]
 }
 
-pub async fn asearch(sold: SaleType, min: i32, max: i32) -> SearchMetadata {
+pub async fn asearch(fetcher: &dyn Fetcher, sold: SaleType, min: i32, max: i32) -> SearchMetadata {
     info!(
         "Searching for sale type: {:?}, min price {} max price{}",
         sold, min, max
     );
     let url = mobile_search_url(LISTING_URL, "1", "", sold, min, max);
     info!("url: {}", url);
-    let html = get_pages_async(&url).await.unwrap();
-    // info!("content: {}", html);
-    let slink = slink(&html);
-    let content = get_header_data(&html).unwrap();
-    let meta = extract_ascii_latin(&content);
-    let re = regex::Regex::new(r" {2,}").unwrap();
-    let split: Vec<&str> = re.split(meta.trim()).collect();
-    info!("split: {:?}", split);
-    let min_price = split[0].replace(' ', "").parse::<u32>().unwrap_or(0);
-    let max_price = split[1].replace(' ', "").parse::<u32>().unwrap_or(0);
-    let total_number = split[2].replace(' ', "").parse::<u32>().unwrap_or(0);
-    SearchMetadata {
-        slink,
-        min_price,
-        max_price,
-        total_number,
-        timestamp: *TIMESTAMP,
-        sale_type: sold,
-    }
+    let html = match fetcher.fetch(&url).await {
+        Ok(page) => page.html,
+        Err(e) => {
+            error!("Search {} failed: {}", url, e);
+            return SearchMetadata::empty(sold);
+        }
+    };
+    SearchMetadata::from_page(&url, &html, sold)
 }
 
 impl SearchMetadata {
@@ -103,30 +96,48 @@ impl SearchMetadata {
         );
         let url = mobile_search_url(LISTING_URL, "1", "", sold, min_price, max_price);
         info!("url: {}", url);
-        let html = get_pages(&url).unwrap();
-        // info!("content: {}", html);
-        let slink = slink(&html);
-        let content = get_header_data(&html).unwrap();
-        let meta = extract_ascii_latin(&content);
-        let re = regex::Regex::new(r" {2,}").unwrap();
-        let split: Vec<&str> = re.split(meta.trim()).collect();
-        info!("split: {:?}", split);
-        let min_price = split[0].replace(' ', "").parse::<u32>().unwrap_or(0);
-        let max_price = split[1].replace(' ', "").parse::<u32>().unwrap_or(0);
-        let total_number = split[2].replace(' ', "").parse::<u32>().unwrap_or(0);
+        match get_pages(&url) {
+            Ok(html) => SearchMetadata::from_page(&url, &html, sold),
+            Err(e) => {
+                error!("Search {} failed: {}", url, e);
+                SearchMetadata::empty(sold)
+            }
+        }
+    }
+
+    /// Reads the first results page of the search `url`. A page without a
+    /// readable summary is a search without results.
+    fn from_page(url: &str, html: &str, sale_type: SaleType) -> Self {
+        let summary = get_header_data(html)
+            .and_then(|description| parse_summary(&description).map_err(|e| e.into()));
+        match summary {
+            Ok(summary) => SearchMetadata {
+                slink: slink(html),
+                timestamp: *TIMESTAMP,
+                total_number: summary.total_number,
+                min_price: summary.min_price,
+                max_price: summary.max_price,
+                sale_type,
+            },
+            Err(e) => {
+                error!("Search {} failed: {}", url, e);
+                SearchMetadata::empty(sale_type)
+            }
+        }
+    }
+
+    /// A search without results, for the searches that could not be made.
+    pub fn empty(sale_type: SaleType) -> Self {
         SearchMetadata {
-            slink,
-            min_price,
-            max_price,
-            total_number,
             timestamp: *TIMESTAMP,
-            sale_type: sold,
+            sale_type,
+            ..Default::default()
         }
     }
 
     pub fn page_numbers(&self) -> u32 {
         let mut pages = self.total_number / 20;
-        if self.total_number % 20 > 0 {
+        if !self.total_number.is_multiple_of(20) {
             pages += 1;
         }
         pages
@@ -138,7 +149,11 @@ mod test {
     use log::info;
 
     use crate::{
-        model::search_metadata::{asearches, astatistic},
+        http::mock::{fixture, MockFetcher},
+        model::{
+            enums::SaleType,
+            search_metadata::{asearch, asearches, astatistic},
+        },
         utils::helpers::configure_log4rs,
     };
 
@@ -146,9 +161,32 @@ mod test {
     async fn test_search() {
         configure_log4rs("config/loggers/dev_log4rs.yml");
         info!("Test index meta");
-        let stats = astatistic().await;
-        let searches = asearches().await;
+        let fetcher = MockFetcher::with_fixtures().await;
+        let stats = astatistic(&fetcher).await;
+        let searches = asearches(&fetcher).await;
         assert_eq!(3, stats.len());
-        assert_eq!(4, searches.len());
+        assert_eq!(7, searches.len());
+        let search = &searches[0];
+        assert_eq!("uafcc1", search.slink);
+        assert_eq!(1100, search.min_price);
+        assert_eq!(25000, search.max_price);
+        assert_eq!(2, search.total_number);
+        assert_eq!(1, search.page_numbers());
+    }
+
+    #[tokio::test]
+    async fn test_search_block_page() {
+        let mut fetcher = MockFetcher::new().await;
+        let captcha = "<html><body><div class=\"g-recaptcha\"></div></body></html>";
+        fetcher.mock_search(captcha).await;
+        let search = asearch(&fetcher, SaleType::INSALE, 1, 5000).await;
+        assert_eq!(0, search.total_number);
+        assert_eq!(0, search.page_numbers());
+
+        let mut fetcher = MockFetcher::new().await;
+        let html = fixture("search.html").replace("до 25 000 лв. Общо 2 обяви", "");
+        fetcher.mock_search(&html).await;
+        let search = asearch(&fetcher, SaleType::INSALE, 1, 5000).await;
+        assert_eq!(0, search.total_number);
     }
 }
@@ -0,0 +1,119 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::selectors::SELECTORS;
+
+/// The technical specification of a vehicle. Labels without a field of their
+/// own are kept in `extra`.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct VehicleSpec {
+    pub category: String,
+    pub colour: String,
+    pub euro_standard: String,
+    /// In cubic centimetres.
+    pub displacement: u16,
+    pub doors: u8,
+    pub vin: String,
+    pub extra: BTreeMap<String, String>,
+}
+
+impl VehicleSpec {
+    pub fn from_pairs(pairs: &[(String, String)]) -> Self {
+        let labels = &SELECTORS.labels;
+        // Read into the fields of the details page itself.
+        let details_labels = [
+            &labels.engine,
+            &labels.gearbox,
+            &labels.power,
+            &labels.millage,
+            &labels.year,
+        ];
+        let mut spec = VehicleSpec::default();
+        for (label, value) in pairs {
+            let label = label.trim_end_matches(':').trim();
+            let value = value.trim();
+            if value.is_empty() || details_labels.iter().any(|l| label.contains(l.as_str())) {
+                continue;
+            }
+            if label == labels.category {
+                spec.category = value.to_string();
+            } else if label == labels.colour {
+                spec.colour = value.to_string();
+            } else if label == labels.euro_standard {
+                spec.euro_standard = value.to_string();
+            } else if label == labels.displacement {
+                spec.displacement = leading_number(value) as u16;
+            } else if label == labels.doors {
+                spec.doors = leading_number(value) as u8;
+            } else if label == labels.vin {
+                spec.vin = value.to_string();
+            } else {
+                spec.extra.insert(label.to_string(), value.to_string());
+            }
+        }
+        spec
+    }
+
+    /// `extra` as a json object, to fit in a single csv column.
+    pub fn extra_as_json(&self) -> String {
+        if self.extra.is_empty() {
+            return String::new();
+        }
+        serde_json::to_string(&self.extra).unwrap_or_default()
+    }
+
+    pub fn extra_from_json(json: &str) -> BTreeMap<String, String> {
+        serde_json::from_str(json).unwrap_or_default()
+    }
+}
+
+/// The digits before the first letter, e.g. 1968 for "1 968 куб.см".
+fn leading_number(value: &str) -> u32 {
+    value
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || c.is_whitespace())
+        .filter(|c| c.is_ascii_digit())
+        .collect::<String>()
+        .parse::<u32>()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VehicleSpec;
+
+    fn pairs(items: &[(&str, &str)]) -> Vec<(String, String)> {
+        items
+            .iter()
+            .map(|(l, v)| (l.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_from_pairs() {
+        let spec = VehicleSpec::from_pairs(&pairs(&[
+            ("Тип двигател", "Дизелов"),
+            ("Категория", "Седан"),
+            ("Цвят", "Черен"),
+            ("Евростандарт", "Евро 5"),
+            ("Кубатура", "1 968 куб.см"),
+            ("Брой врати", "4/5"),
+            ("VIN", "WAUZZZ8K1AA000001"),
+            ("Климатик", "Да"),
+            ("Пробег", "232167 км"),
+        ]));
+        assert_eq!("Седан", spec.category);
+        assert_eq!("Черен", spec.colour);
+        assert_eq!("Евро 5", spec.euro_standard);
+        assert_eq!(1968, spec.displacement);
+        assert_eq!(4, spec.doors);
+        assert_eq!("WAUZZZ8K1AA000001", spec.vin);
+        assert_eq!(1, spec.extra.len());
+        assert_eq!("Да", spec.extra["Климатик"]);
+
+        let json = spec.extra_as_json();
+        assert_eq!(spec.extra, VehicleSpec::extra_from_json(&json));
+        assert!(VehicleSpec::default().extra_as_json().is_empty());
+    }
+}
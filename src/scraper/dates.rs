@@ -0,0 +1,141 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+/// The months as written on the site, in the genitive used in the dates.
+pub const MONTHS: [&str; 12] = [
+    "януари",
+    "февруари",
+    "март",
+    "април",
+    "май",
+    "юни",
+    "юли",
+    "август",
+    "септември",
+    "октомври",
+    "ноември",
+    "декември",
+];
+
+lazy_static! {
+    /// `в 10:32 часа на 12 октомври, 2023 год.`
+    static ref TIMESTAMP_REGEX: Regex =
+        Regex::new(r"в\s+(\d{1,2}):(\d{2})\s+часа\s+на\s+(\d{1,2})\s+(\p{L}+),?\s+(\d{4})").unwrap();
+    /// `2015 г., м. 3`
+    static ref YEAR_MONTH_REGEX: Regex =
+        Regex::new(r"^(\d{4})\s*г\.?,?\s*м\.?\s*(\d{1,2})$").unwrap();
+    /// `март 2015 г.`
+    static ref MONTH_NAME_REGEX: Regex = Regex::new(r"^(\p{L}+\.?)\s+(\d{4})(?:\s*г\.?)?$").unwrap();
+    /// `03.2015`, `3/2015` or `03-2015 г.`
+    static ref MONTH_YEAR_REGEX: Regex =
+        Regex::new(r"^(\d{1,2})[./-](\d{4})(?:\s*г\.?)?$").unwrap();
+    /// `2015-03`
+    static ref ISO_MONTH_REGEX: Regex = Regex::new(r"^(\d{4})[./-](\d{1,2})$").unwrap();
+    /// `2015 г.`
+    static ref YEAR_REGEX: Regex = Regex::new(r"^(\d{4})(?:\s*г\.?)?$").unwrap();
+}
+
+/// The number of the month, from its name or an abbreviation such as `окт.`.
+pub fn month_of(name: &str) -> Option<u32> {
+    let name = name.trim().trim_end_matches('.').to_lowercase();
+    if name.chars().count() < 3 {
+        return None;
+    }
+    MONTHS
+        .iter()
+        .position(|month| month.starts_with(&name))
+        .map(|index| index as u32 + 1)
+}
+
+/// The production year and month, in any of the formats of the site. `None`
+/// when the text is not a date or the month or the year is out of range.
+pub fn parse_production(text: &str) -> Option<(u16, Option<u8>)> {
+    let text = text.trim();
+    let (year, month) = if let Some(captures) = YEAR_MONTH_REGEX.captures(text) {
+        (
+            captures[1].to_string(),
+            Some(captures[2].parse::<u32>().ok()?),
+        )
+    } else if let Some(captures) = MONTH_NAME_REGEX.captures(text) {
+        (captures[2].to_string(), Some(month_of(&captures[1])?))
+    } else if let Some(captures) = MONTH_YEAR_REGEX.captures(text) {
+        (
+            captures[2].to_string(),
+            Some(captures[1].parse::<u32>().ok()?),
+        )
+    } else if let Some(captures) = ISO_MONTH_REGEX.captures(text) {
+        (
+            captures[1].to_string(),
+            Some(captures[2].parse::<u32>().ok()?),
+        )
+    } else {
+        (YEAR_REGEX.captures(text)?[1].to_string(), None)
+    };
+    let year = year
+        .parse::<u16>()
+        .ok()
+        .filter(|y| (1900..=2100).contains(y))?;
+    match month {
+        Some(month) if !(1..=12).contains(&month) => None,
+        month => Some((year, month.map(|m| m as u8))),
+    }
+}
+
+/// The time following `label` in the text, e.g. `Публикувана в 10:32 часа на
+/// 12 октомври, 2023 год.`. The site shows the local time of Sofia.
+pub fn parse_timestamp(text: &str, label: &str) -> Option<NaiveDateTime> {
+    let start = text.find(label)? + label.len();
+    let captures = TIMESTAMP_REGEX.captures(&text[start..])?;
+    let number = |index: usize| captures[index].parse::<u32>().ok();
+    let date = NaiveDate::from_ymd_opt(
+        captures[5].parse::<i32>().ok()?,
+        month_of(&captures[4])?,
+        number(3)?,
+    )?;
+    date.and_hms_opt(number(1)?, number(2)?, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::{month_of, parse_production, parse_timestamp};
+
+    #[test]
+    fn test_parse_timestamp() {
+        let text = "Публикувана в 10:32 часа на 12 октомври, 2023 год.\n\
+                    Коригирана в 9:05 часа на 1 май 2024 год.";
+        assert_eq!(
+            NaiveDate::from_ymd_opt(2023, 10, 12).and_then(|d| d.and_hms_opt(10, 32, 0)),
+            parse_timestamp(text, "Публикувана")
+        );
+        assert_eq!(
+            NaiveDate::from_ymd_opt(2024, 5, 1).and_then(|d| d.and_hms_opt(9, 5, 0)),
+            parse_timestamp(text, "Коригирана")
+        );
+        assert_eq!(None, parse_timestamp(text, "Изтрита"));
+        assert_eq!(
+            None,
+            parse_timestamp("Публикувана в 25:00 часа на 1 май 2024", "Публикувана")
+        );
+        assert_eq!(Some(10), month_of("Окт."));
+        assert_eq!(Some(6), month_of("юни"));
+        assert_eq!(None, month_of("ю"));
+    }
+
+    #[test]
+    fn test_parse_production() {
+        assert_eq!(Some((2010, Some(6))), parse_production("юни 2010 г."));
+        assert_eq!(Some((2015, Some(3))), parse_production("Март 2015"));
+        assert_eq!(Some((2015, Some(3))), parse_production("03.2015"));
+        assert_eq!(Some((2015, Some(3))), parse_production("3/2015 г."));
+        assert_eq!(Some((2015, Some(3))), parse_production("2015 г., м. 3"));
+        assert_eq!(Some((2015, Some(11))), parse_production("2015-11"));
+        assert_eq!(Some((2015, None)), parse_production(" 2015 г. "));
+        assert_eq!(None, parse_production("13.2015"));
+        assert_eq!(None, parse_production("около 2015"));
+        assert_eq!(None, parse_production("32015"));
+        assert_eq!(None, parse_production(""));
+    }
+}
@@ -0,0 +1,674 @@
+use std::{fmt, str::FromStr};
+
+use chrono::NaiveDateTime;
+use log::debug;
+use reqwest::Url;
+use scraper::{Html, Selector};
+
+use super::{
+    dates::{parse_production, parse_timestamp},
+    health::FieldHit,
+    price::{parse_price, Price},
+};
+
+use crate::{
+    config::{
+        equipment::get_equipment_as_u64,
+        gazetteer::{Place, GAZETTEER},
+        makes::MAKES,
+        selectors::{Css, SELECTORS},
+    },
+    http::client::normalize_url,
+    model::{
+        enums::{Dealer, Engine, Gearbox},
+        spec::VehicleSpec,
+    },
+    utils::helpers::extract_integers,
+};
+
+/// Everything read from a details page.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DetailsPage {
+    pub id: String,
+    pub make: String,
+    pub model: String,
+    pub variant: Option<String>,
+    /// The header the make and model were read from.
+    pub title: String,
+    /// False when the title is not in the make catalogue.
+    pub title_matched: bool,
+    pub price: Price,
+    pub millage: u32,
+    pub production_year: u16,
+    /// `None` when only the year is shown.
+    pub production_month: Option<u8>,
+    pub engine: Engine,
+    pub gearbox: Gearbox,
+    pub power: u16,
+    pub phone: String,
+    pub location: String,
+    pub place: Place,
+    pub view_count: u32,
+    /// Sofia local time.
+    pub published_at: Option<NaiveDateTime>,
+    pub edited_at: Option<NaiveDateTime>,
+    pub equipment: u64,
+    /// The full size photo urls, in the order of the gallery.
+    pub images: Vec<String>,
+    pub description: String,
+    pub top: bool,
+    pub vip: bool,
+    pub sold: bool,
+    pub seller: Seller,
+    pub spec: VehicleSpec,
+    /// Which of the selectors and labels matched.
+    pub hits: Vec<FieldHit>,
+    /// Values that could not be read. Their fields keep the defaults.
+    pub errors: Vec<ParseError>,
+}
+
+/// The text a selector or label of a details page matched, before the
+/// parser converted it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawMatch {
+    pub field: &'static str,
+    pub selector: String,
+    /// `None` when nothing matched.
+    pub raw: Option<String>,
+}
+
+/// Who placed the advert. Only dealers have a name and a profile.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Seller {
+    pub dealer: Dealer,
+    pub name: String,
+    pub profile_url: String,
+    pub profile_id: String,
+    /// As shown on the page, e.g. `03.2015`.
+    pub registered_since: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// The page says the advert has been removed.
+    NotFound,
+    /// Nothing matched the selector of a required field.
+    Missing {
+        field: &'static str,
+        selector: String,
+    },
+    /// The text of the field could not be read.
+    Invalid {
+        field: &'static str,
+        selector: String,
+        value: String,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::NotFound => write!(f, "advert not found"),
+            ParseError::Missing { field, selector } => {
+                write!(f, "missing {} ({})", field, selector)
+            }
+            ParseError::Invalid {
+                field,
+                selector,
+                value,
+            } => write!(f, "invalid {} '{}' ({})", field, value, selector),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses the details page of the advert `id`.
+///
+/// Make, model, engine and gearbox are required, the rest of the fields fall
+/// back to their defaults when they are not on the page, or to the defaults
+/// and an entry in `errors` when their value cannot be read.
+pub fn parse_details(id: &str, html: &str) -> Result<DetailsPage, ParseError> {
+    parse_details_with(id, html, &mut vec![])
+}
+
+/// Same as `parse_details`, and adds what every selector and label matched
+/// to `matches`. They are all read before anything is converted, so a page
+/// the parser gives up on has them too.
+pub fn parse_details_with(
+    id: &str,
+    html: &str,
+    matches: &mut Vec<RawMatch>,
+) -> Result<DetailsPage, ParseError> {
+    let selectors = &SELECTORS.details;
+    let labels = &SELECTORS.labels;
+    if html.contains(&labels.not_found) {
+        return Err(ParseError::NotFound);
+    }
+    let document = Html::parse_document(html);
+    let header = text_of(&document, &selectors.header, " ");
+    let specs = specs(&document);
+    let price_text = text_of(&document, &selectors.price, " ");
+    let view_count_text = text_of(&document, &selectors.view_count, " ");
+    let dates = text_of(&document, &selectors.dates, " ");
+    let phone_text = text_of(&document, &selectors.phone, "");
+    let address = text_of(&document, &selectors.address, "");
+    let dealer = text_of(&document, &selectors.dealer, " ");
+    let dealer_url = document
+        .select(&selectors.dealer_link)
+        .next()
+        .and_then(|a| a.value().attr("href"))
+        .map(|href| href.to_string());
+    let dealer_since = text_of(&document, &selectors.dealer_since, " ");
+    let sold = text_of(&document, &selectors.sold, " ");
+    let top = text_of(&document, &selectors.top, " ");
+    let vip = text_of(&document, &selectors.vip, " ");
+    let extras = document
+        .select(&selectors.equipment)
+        .map(|div| {
+            div.text()
+                .collect::<String>()
+                .replace('•', "")
+                .trim()
+                .to_string()
+        })
+        .collect::<Vec<String>>();
+    let mut images: Vec<String> = vec![];
+    for img in document.select(&selectors.images) {
+        let src = img.value().attr("data-src").or(img.value().attr("src"));
+        if let Some(url) = src.map(normalize_url) {
+            if !images.contains(&url) {
+                images.push(url);
+            }
+        }
+    }
+    let description = document.select(&selectors.description).next().map(|div| {
+        div.text()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    });
+
+    let mut matched = |field, selector: &str, raw: Option<&str>| {
+        matches.push(RawMatch {
+            field,
+            selector: selector.to_string(),
+            raw: raw.map(|raw| raw.to_string()),
+        })
+    };
+    matched("make", &selectors.header.css, header.as_deref());
+    matched("model", &selectors.header.css, header.as_deref());
+    for (field, label) in [
+        ("engine", &labels.engine),
+        ("gearbox", &labels.gearbox),
+        ("power", &labels.power),
+        ("millage", &labels.millage),
+        ("year", &labels.year),
+        ("category", &labels.category),
+        ("colour", &labels.colour),
+        ("euro_standard", &labels.euro_standard),
+        ("displacement", &labels.displacement),
+        ("doors", &labels.doors),
+        ("vin", &labels.vin),
+    ] {
+        let selector = format!("{} '{}'", selectors.specs.css, label);
+        matched(field, &selector, spec(&specs, label));
+    }
+    let dated = |label: &str| dates.as_deref().filter(|dates| dates.contains(label));
+    matched("price", &selectors.price.css, price_text.as_deref());
+    matched(
+        "view_count",
+        &selectors.view_count.css,
+        view_count_text.as_deref(),
+    );
+    matched(
+        "published_at",
+        &selectors.dates.css,
+        dated(&labels.published),
+    );
+    matched("edited_at", &selectors.dates.css, dated(&labels.edited));
+    matched("phone", &selectors.phone.css, phone_text.as_deref());
+    matched("address", &selectors.address.css, address.as_deref());
+    matched("dealer", &selectors.dealer.css, dealer.as_deref());
+    matched(
+        "dealer_url",
+        &selectors.dealer_link.css,
+        dealer_url.as_deref(),
+    );
+    matched(
+        "dealer_since",
+        &selectors.dealer_since.css,
+        dealer_since.as_deref(),
+    );
+    matched("sold", &selectors.sold.css, sold.as_deref());
+    matched("top", &selectors.top.css, top.as_deref());
+    matched("vip", &selectors.vip.css, vip.as_deref());
+    let all = |values: &[String]| (!values.is_empty()).then(|| values.join("\n"));
+    matched(
+        "equipment",
+        &selectors.equipment.css,
+        all(&extras).as_deref(),
+    );
+    matched("images", &selectors.images.css, all(&images).as_deref());
+    matched(
+        "description",
+        &selectors.description.css,
+        description.as_deref(),
+    );
+
+    let title = MAKES.resolve(&header.ok_or_else(|| ParseError::Missing {
+        field: "make",
+        selector: selectors.header.css.clone(),
+    })?);
+    if title.model.is_empty() {
+        return Err(invalid("model", &selectors.header, &title.title));
+    }
+    let engine = required_spec(&specs, &labels.engine, "engine")?;
+    let gearbox = required_spec(&specs, &labels.gearbox, "gearbox")?;
+    let mut errors = vec![];
+    let power = match spec(&specs, &labels.power) {
+        Some(value) => match extract_integers(value).first() {
+            Some(power) => *power as u16,
+            None => {
+                errors.push(invalid("power", &selectors.specs, value));
+                0
+            }
+        },
+        None => 0,
+    };
+    let millage = match spec(&specs, &labels.millage) {
+        Some(value) if value.chars().any(|c| c.is_ascii_digit()) => value
+            .split_whitespace()
+            .next()
+            .map(|value| digits(value) as u32)
+            .unwrap_or(0),
+        Some(value) => {
+            errors.push(invalid("millage", &selectors.specs, value));
+            0
+        }
+        None => 0,
+    };
+    let (production_year, production_month) = match spec(&specs, &labels.year) {
+        Some(value) => parse_production(value).unwrap_or_else(|| {
+            errors.push(invalid("year", &selectors.specs, value));
+            (0, None)
+        }),
+        None => (0, None),
+    };
+
+    let dates = dates.unwrap_or_default();
+    let published_at = parse_timestamp(&dates, &labels.published);
+    let edited_at = parse_timestamp(&dates, &labels.edited);
+    let spec_hit = |field, label: &str| {
+        let selector = format!("{} '{}'", selectors.specs.css, label);
+        FieldHit::new(field, &selector, spec(&specs, label).is_some())
+    };
+    let hits = vec![
+        FieldHit::new("make", &selectors.header.css, true),
+        FieldHit::new("specs", &selectors.specs.css, !specs.is_empty()),
+        spec_hit("engine", &labels.engine),
+        spec_hit("gearbox", &labels.gearbox),
+        spec_hit("power", &labels.power),
+        spec_hit("millage", &labels.millage),
+        spec_hit("year", &labels.year),
+        FieldHit::new("price", &selectors.price.css, price_text.is_some()),
+        FieldHit::new(
+            "view_count",
+            &selectors.view_count.css,
+            view_count_text.is_some(),
+        ),
+        FieldHit::new("published_at", &selectors.dates.css, published_at.is_some()),
+        FieldHit::new("phone", &selectors.phone.css, phone_text.is_some()),
+        FieldHit::new("address", &selectors.address.css, address.is_some()),
+        FieldHit::new("equipment", &selectors.equipment.css, !extras.is_empty()),
+    ];
+
+    let price = price_text
+        .map(|text| parse_price(&text))
+        .unwrap_or_default();
+    let view_count = view_count_text
+        .and_then(|text| extract_integers(&text).first().copied())
+        .unwrap_or(0);
+    let phone = phone_text.unwrap_or_else(|| "0000000000".to_string());
+    let location = address
+        .as_ref()
+        .map(|address| address.split(',').next().unwrap_or("").to_string())
+        .unwrap_or_else(|| "Unknown".to_string());
+    let place = address
+        .map(|address| GAZETTEER.resolve(&address))
+        .unwrap_or_default();
+    let equipment = if extras.is_empty() {
+        0
+    } else {
+        get_equipment_as_u64(extras)
+    };
+
+    let vehicle_spec = VehicleSpec::from_pairs(&specs);
+    for (field, label, value) in [
+        (
+            "displacement",
+            &labels.displacement,
+            vehicle_spec.displacement as u32,
+        ),
+        ("doors", &labels.doors, vehicle_spec.doors as u32),
+    ] {
+        if let Some(raw) = spec(&specs, label).filter(|_| value == 0) {
+            errors.push(invalid(field, &selectors.specs, raw));
+        }
+    }
+
+    let top = top.is_some();
+    let vip = !top && vip.is_some();
+
+    Ok(DetailsPage {
+        id: id.to_string(),
+        make: title.make,
+        model: title.model,
+        variant: title.variant,
+        title: title.title,
+        title_matched: title.matched,
+        price,
+        millage,
+        production_year,
+        production_month,
+        engine: Engine::from_str(engine).unwrap_or(Engine::NotAvailable),
+        gearbox: Gearbox::from_str(gearbox).unwrap_or(Gearbox::NotAvailable),
+        power,
+        phone,
+        location,
+        place,
+        view_count,
+        published_at,
+        edited_at,
+        equipment,
+        images,
+        description: description.unwrap_or_default(),
+        top,
+        vip,
+        sold: sold.is_some(),
+        seller: seller(dealer, dealer_url, dealer_since),
+        spec: vehicle_spec,
+        hits,
+        errors,
+    })
+}
+
+fn seller(name: Option<String>, profile_url: Option<String>, since: Option<String>) -> Seller {
+    let Some(name) = name else {
+        return Seller {
+            dealer: Dealer::PRIVATE,
+            ..Default::default()
+        };
+    };
+    let profile_url = profile_url.unwrap_or_default();
+    let registered_since = since
+        .and_then(|text| {
+            text.find(|c: char| c.is_ascii_digit())
+                .map(|start| text[start..].trim().to_string())
+        })
+        .unwrap_or_default();
+    Seller {
+        dealer: Dealer::DEALER,
+        name: name.trim().to_string(),
+        profile_id: profile_id(&profile_url),
+        profile_url,
+        registered_since,
+    }
+}
+
+/// The subdomain of a dealer page like `https://autoplovdiv.mobile.bg`, or
+/// the last segment of its path.
+fn profile_id(profile_url: &str) -> String {
+    let url =
+        match Url::parse(profile_url).or_else(|_| Url::parse(&format!("https:{}", profile_url))) {
+            Ok(url) => url,
+            Err(_) => return String::new(),
+        };
+    let subdomain = url
+        .host_str()
+        .and_then(|host| host.strip_suffix(".mobile.bg"))
+        .filter(|subdomain| *subdomain != "www");
+    match subdomain {
+        Some(subdomain) => subdomain.to_string(),
+        None => url
+            .path_segments()
+            .and_then(|mut segments| segments.rfind(|s| !s.is_empty()))
+            .unwrap_or_default()
+            .to_string(),
+    }
+}
+
+/// Label and value pairs of the specification list, every label with the
+/// item after it. A label followed by another known label has lost its value
+/// and is skipped, so a missing or extra item does not shift the next pairs.
+pub fn specs(document: &Html) -> Vec<(String, String)> {
+    let items = document
+        .select(&SELECTORS.details.specs)
+        .map(|li| li.text().collect::<String>().trim().to_string())
+        .collect::<Vec<String>>();
+    let mut pairs = vec![];
+    let mut items = items.into_iter().peekable();
+    while let Some(label) = items.next() {
+        match items.next_if(|value| !is_spec_label(value)) {
+            Some(value) => pairs.push((label, value)),
+            None => debug!("Skipping the spec '{}' without a value", label),
+        }
+    }
+    pairs
+}
+
+fn is_spec_label(text: &str) -> bool {
+    let labels = &SELECTORS.labels;
+    [
+        &labels.engine,
+        &labels.gearbox,
+        &labels.power,
+        &labels.millage,
+        &labels.year,
+        &labels.category,
+        &labels.colour,
+        &labels.euro_standard,
+        &labels.displacement,
+        &labels.doors,
+        &labels.vin,
+    ]
+    .iter()
+    .any(|label| text.contains(label.as_str()))
+}
+
+fn spec<'a>(specs: &'a [(String, String)], label: &str) -> Option<&'a str> {
+    specs
+        .iter()
+        .find(|(l, _)| l.contains(label))
+        .map(|(_, value)| value.as_str())
+}
+
+fn required_spec<'a>(
+    specs: &'a [(String, String)],
+    label: &str,
+    field: &'static str,
+) -> Result<&'a str, ParseError> {
+    spec(specs, label).ok_or_else(|| ParseError::Missing {
+        field,
+        selector: SELECTORS.details.specs.css.clone(),
+    })
+}
+
+fn text_of(document: &Html, selector: &Selector, separator: &str) -> Option<String> {
+    document
+        .select(selector)
+        .next()
+        .map(|element| element.text().collect::<Vec<_>>().join(separator))
+}
+
+/// The digits of the text as a number, zero when there are none.
+fn digits(text: &str) -> u64 {
+    text.chars()
+        .filter(|c| c.is_ascii_digit())
+        .collect::<String>()
+        .parse::<u64>()
+        .unwrap_or(0)
+}
+
+fn invalid(field: &'static str, selector: &Css, value: &str) -> ParseError {
+    ParseError::Invalid {
+        field,
+        selector: selector.css.clone(),
+        value: value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::{
+        http::mock::fixture,
+        model::enums::{Currency, Dealer, Engine, Gearbox},
+    };
+
+    use super::{parse_details, profile_id, ParseError};
+
+    #[test]
+    fn test_parse_details() {
+        let details = parse_details("1001", &fixture("details.html")).unwrap();
+        assert_eq!("1001", details.id);
+        assert_eq!("Audi", details.make);
+        assert_eq!("A4", details.model);
+        assert_eq!(Some("2.0 TDI".to_string()), details.variant);
+        assert_eq!("Audi A4 2.0 TDI", details.title);
+        assert!(details.title_matched);
+        assert_eq!(Engine::Diesel, details.engine);
+        assert_eq!(Gearbox::Manual, details.gearbox);
+        assert_eq!(143, details.power);
+        assert_eq!(232167, details.millage);
+        assert_eq!(
+            (2010, Some(6)),
+            (details.production_year, details.production_month)
+        );
+        assert_eq!(18400, details.price.amount);
+        assert_eq!(Currency::BGN, details.price.currency);
+        assert_eq!(1746, details.view_count);
+        assert_eq!("гр. Пловдив", details.location);
+        assert_eq!("Пловдив", details.place.city);
+        assert_eq!("Пловдив", details.place.region);
+        assert!(details.place.matched);
+        assert_eq!("0897801432", details.phone);
+        assert!(!details.sold);
+        assert_eq!(
+            vec![
+                "https://cdn.mobile.bg/photos/big/1001_1.webp",
+                "https://cdn.mobile.bg/photos/big/1001_2.webp"
+            ],
+            details.images
+        );
+        assert_eq!(
+            "Колата е в отлично състояние.\nОбслужена, с нови гуми.",
+            details.description
+        );
+        assert_eq!(Dealer::PRIVATE, details.seller.dealer);
+        assert!(details.seller.name.is_empty());
+        assert!(details.hits.iter().all(|hit| hit.hit));
+        assert_eq!(13, details.hits.len());
+        assert_eq!(
+            NaiveDate::from_ymd_opt(2023, 10, 12).and_then(|d| d.and_hms_opt(10, 32, 0)),
+            details.published_at
+        );
+        assert_eq!(
+            NaiveDate::from_ymd_opt(2023, 10, 14).and_then(|d| d.and_hms_opt(14, 5, 0)),
+            details.edited_at
+        );
+        assert_eq!("Седан", details.spec.category);
+        assert_eq!("Черен", details.spec.colour);
+        assert_eq!("Евро 5", details.spec.euro_standard);
+        assert_eq!(1968, details.spec.displacement);
+        assert_eq!("Автоматичен", details.spec.extra["Климатик"]);
+    }
+
+    #[test]
+    fn test_parse_dealer() {
+        let dealer = "<div class=\"AG\"><strong>Авто Пловдив</strong>\
+            <a href=\"//autoplovdiv.mobile.bg\">Всички обяви</a>\
+            <div class=\"since\">На mobile.bg от 03.2015</div></div>";
+        let html = fixture("details.html").replace("<div class=\"contacts\">", dealer);
+        let seller = parse_details("1", &html).unwrap().seller;
+        assert_eq!(Dealer::DEALER, seller.dealer);
+        assert_eq!("Авто Пловдив", seller.name);
+        assert_eq!("//autoplovdiv.mobile.bg", seller.profile_url);
+        assert_eq!("autoplovdiv", seller.profile_id);
+        assert_eq!("03.2015", seller.registered_since);
+        assert_eq!("4711", profile_id("https://www.mobile.bg/dealers/4711"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Err(ParseError::NotFound),
+            parse_details("1", &fixture("not_found.html"))
+        );
+        assert_eq!(
+            Err(ParseError::Missing {
+                field: "make",
+                selector: "h1".to_string()
+            }),
+            parse_details("1", &fixture("search.html"))
+        );
+        let html = fixture("details.html").replace("<li>Тип двигател</li><li>Дизелов</li>", "");
+        assert_eq!(
+            Err(ParseError::Missing {
+                field: "engine",
+                selector: "ul.dilarData li".to_string()
+            }),
+            parse_details("1", &html)
+        );
+        let html = fixture("details.html").replace("юни 2010 г.", "32015");
+        let details = parse_details("1", &html).unwrap();
+        assert_eq!(
+            (0, None),
+            (details.production_year, details.production_month)
+        );
+        assert_eq!(18400, details.price.amount);
+        assert_eq!(
+            vec!["invalid year '32015' (ul.dilarData li)".to_string()],
+            details
+                .errors
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+        );
+        // The label matched, so the selectors have not drifted.
+        assert!(details.hits.iter().any(|h| h.field == "year" && h.hit));
+        let html = fixture("details.html").replace("143 к.с.", "n/a");
+        let details = parse_details("1", &html).unwrap();
+        assert_eq!((0, 232167), (details.power, details.millage));
+        assert_eq!(1, details.errors.len());
+        assert_eq!(
+            "invalid power 'n/a' (ul.dilarData li)",
+            details.errors[0].to_string()
+        );
+    }
+
+    #[test]
+    fn test_unpaired_specs() {
+        // The power lost its value and an item without a label came in.
+        let html = fixture("details.html")
+            .replace("<li>143 к.с.</li>", "")
+            .replace("<li>Категория</li>", "<li>Нов внос</li><li>Категория</li>");
+        let details = parse_details("1", &html).unwrap();
+        assert_eq!(0, details.power);
+        assert_eq!(Gearbox::Manual, details.gearbox);
+        assert_eq!(232167, details.millage);
+        assert_eq!("Седан", details.spec.category);
+        assert_eq!("Черен", details.spec.colour);
+        assert!(details.errors.is_empty());
+
+        let html = fixture("details.html").replace("1968 куб.см", "н.д.");
+        let details = parse_details("1", &html).unwrap();
+        assert_eq!(0, details.spec.displacement);
+        assert_eq!(
+            "invalid displacement 'н.д.' (ul.dilarData li)",
+            details.errors[0].to_string()
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use std::{collections::BTreeMap, fmt};
+
+use crate::config::app_config::ParserHealthConfig;
+
+use super::details::ParseError;
+
+/// Whether the selector of a field found anything on a page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldHit {
+    pub field: &'static str,
+    pub selector: String,
+    pub hit: bool,
+}
+
+impl FieldHit {
+    pub fn new(field: &'static str, selector: &str, hit: bool) -> Self {
+        FieldHit {
+            field,
+            selector: selector.to_string(),
+            hit,
+        }
+    }
+
+    /// The field a parse error failed on, as a miss.
+    pub fn from_error(error: &ParseError) -> Option<Self> {
+        match error {
+            ParseError::NotFound => None,
+            ParseError::Missing { field, selector } => Some(FieldHit::new(field, selector, false)),
+            ParseError::Invalid {
+                field, selector, ..
+            } => Some(FieldHit::new(field, selector, false)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Count {
+    selector: String,
+    hits: usize,
+    pages: usize,
+}
+
+/// Hits and misses of every field over a run.
+#[derive(Debug, Clone, Default)]
+pub struct SelectorHealth {
+    fields: BTreeMap<&'static str, Count>,
+    pages: usize,
+}
+
+impl SelectorHealth {
+    /// Adds the fields of one page.
+    pub fn record(&mut self, hits: &[FieldHit]) {
+        self.pages += 1;
+        for hit in hits {
+            let count = self.fields.entry(hit.field).or_default();
+            count.selector = hit.selector.clone();
+            count.pages += 1;
+            if hit.hit {
+                count.hits += 1;
+            }
+        }
+    }
+
+    /// The share of the pages checked for the field that it was found on.
+    /// A page the parser gave up on is only checked for the failed field.
+    pub fn hit_rate(&self, field: &str) -> Option<f64> {
+        let count = self.fields.get(field)?;
+        Some(count.hits as f64 / count.pages.max(1) as f64)
+    }
+
+    /// The required fields found on too few pages, with their rates.
+    pub fn drifted(&self, config: &ParserHealthConfig) -> Vec<String> {
+        if self.pages < config.min_pages.max(1) {
+            return vec![];
+        }
+        config
+            .required
+            .iter()
+            .filter_map(|field| {
+                let rate = self.hit_rate(field).unwrap_or(0.0);
+                (rate < config.min_hit_rate).then(|| format!("{} {:.1}%", field, rate * 100.0))
+            })
+            .collect()
+    }
+}
+
+/// One line per field, e.g. `phone (div.phone) matched 0 of 4812 pages`.
+impl fmt::Display for SelectorHealth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (field, count) in self.fields.iter() {
+            writeln!(
+                f,
+                "{} ({}) matched {} of {} pages",
+                field, count.selector, count.hits, count.pages
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::app_config::ParserHealthConfig;
+
+    use super::{FieldHit, SelectorHealth};
+
+    #[test]
+    fn test_drifted() {
+        let mut health = SelectorHealth::default();
+        for page in 0..10 {
+            health.record(&[
+                FieldHit::new("make", "h1", true),
+                FieldHit::new("phone", "div.phone", false),
+                FieldHit::new("price", "span#details_price", page < 5),
+            ]);
+        }
+        health.record(&[FieldHit::new("make", "h1", false)]);
+        let config = ParserHealthConfig {
+            min_hit_rate: 0.8,
+            min_pages: 5,
+            required: vec!["make".to_string(), "price".to_string()],
+        };
+        assert_eq!(Some(10.0 / 11.0), health.hit_rate("make"));
+        assert_eq!(vec!["price 50.0%".to_string()], health.drifted(&config));
+        assert!(health
+            .to_string()
+            .contains("phone (div.phone) matched 0 of 10 pages"));
+
+        let config = ParserHealthConfig {
+            min_pages: 100,
+            ..config
+        };
+        assert!(health.drifted(&config).is_empty());
+    }
+}
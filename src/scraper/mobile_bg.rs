@@ -1,214 +1,73 @@
-use crate::config::equipment::get_equipment_as_u64;
-use crate::model::enums::Currency;
+use crate::config::selectors::SELECTORS;
+use crate::http::charset;
+use crate::http::client::{normalize_url, BLOCKING_CLIENT};
+use crate::http::fetcher::{content_type, FetchError, Fetcher};
 use crate::utils::helpers::extract_ascii_latin;
-use crate::utils::helpers::extract_integers;
-use crate::ENGINE_TXT;
-use crate::GEARBOX_TXT;
-use crate::NOT_FOUND_MSG;
-use crate::POWER_TXT;
-use crate::{BROWSER_USER_AGENT, MILLAGE_TXT, YEAR_TXT};
-
-use encoding_rs::{UTF_8, WINDOWS_1251};
+
+use crate::model::records::ListingRecord;
+use crate::CREATED_ON;
+
+use super::{
+    details::{parse_details, DetailsPage, ParseError},
+    price::parse_price,
+};
+
 use log::{debug, error};
 
 use regex::Regex;
-use scraper::{ElementRef, Html, Selector};
+use scraper::{ElementRef, Html};
 
-use lazy_static::lazy_static;
 use std::collections::HashMap;
-
-lazy_static! {
-    static ref TABLERESET_SELECTOR: Selector = Selector::parse("table.tablereset").unwrap();
-    static ref DETAILS_HEADER_SELECTOR: Selector = Selector::parse("h1").unwrap();
-    static ref KAPARO_SELECTOR: Selector = Selector::parse("div.kaparo").unwrap();
-    static ref TOP_SELECTOR: Selector = Selector::parse("td.img.TOP").unwrap();
-    static ref VIP_SELECTOR: Selector = Selector::parse("td.img.VIP").unwrap();
-    static ref PHONE_SELECTOR: Selector = Selector::parse("div.phone").unwrap();
-    static ref DEALER_SELECTOR: Selector = Selector::parse("div.AG > strong").unwrap();
-    static ref ADDRESS_SELECTOR: Selector = Selector::parse("div.adress").unwrap();
-    static ref DILAR_SELECTOR: Selector = Selector::parse("ul.dilarData").unwrap();
-    static ref PRICE_SELECTOR: Selector = Selector::parse("span.price").unwrap();
-    static ref TITLE_SELECTOR: Selector = Selector::parse("div.title").unwrap();
-    static ref ADV_ACT_SELECTOR: Selector = Selector::parse("span.advact").unwrap();
-    static ref DETAILS_PRICE_SELECTOR: Selector = Selector::parse("span#details_price").unwrap();
-    static ref META_DESC_SELECTOR: Selector = Selector::parse("meta[name=description]").unwrap();
-    static ref PAGE_NUMBERS_SELECTOR: Selector = Selector::parse("a.pageNumbers").unwrap();
-    static ref TOP_MMM_SELECTOR: Selector = Selector::parse("td.valgtop a.mmm").unwrap();
-    static ref INPUT_TYPE_HIDDEN: Selector = Selector::parse("input[name=slink]").unwrap();
-    static ref DIV_MARGIN_SELECTOR: Selector =
-        Selector::parse("div[style*=\"margin-bottom:5px;\"]").unwrap();
+use std::fmt;
+
+/// Why the details of an advert could not be read.
+#[derive(Debug)]
+pub enum DetailsError {
+    NoId(String),
+    NotFound,
+    Fetch(FetchError),
+    Parse(ParseError),
 }
 
-pub async fn details2map(url: &str) -> HashMap<String, String> {
-    debug!("Processing details {}", url);
-
-    let mut map = HashMap::new();
-    let html = match get_pages_async(url).await {
-        Ok(v) => v,
-        Err(e) => {
-            error!("Error getting details {}", e);
-            return map;
-        }
-    };
-    if let Some(adv_value) = get_id_from_url(url.to_string()) {
-        map.insert("id".to_string(), adv_value);
-        if html.contains(NOT_FOUND_MSG) {
-            map.insert("error".to_string(), "Not found".to_string());
-            return map;
-        }
-    } else {
-        return HashMap::new();
-    }
-
-    let document = Html::parse_document(&html);
-
-    map.insert("type".to_string(), "DETAILS".to_string());
-
-    let phone = if let Some(txt) = document.select(&PHONE_SELECTOR).next() {
-        txt.text().collect::<Vec<_>>().join("")
-    } else {
-        "0000000000".to_string()
-    };
-
-    let address = if let Some(txt) = document.select(&ADDRESS_SELECTOR).next() {
-        let location = txt.text().collect::<Vec<_>>().join("");
-        location.split(',').collect::<Vec<_>>()[0].to_string()
-    } else {
-        "Unknown".to_string()
-    };
-    let is_dealer = document.select(&DEALER_SELECTOR).next().is_some();
-    map.insert("phone".to_string(), phone);
-    map.insert("dealer".to_string(), (!is_dealer).to_string());
-    map.insert("location".to_string(), address);
-
-    if let Some(h1_element) = document.select(&DETAILS_HEADER_SELECTOR).next() {
-        let text = h1_element.text().collect::<Vec<_>>().join(";");
-        let values = text.split_whitespace().collect::<Vec<&str>>();
-
-        for v in values.clone() {
-            debug!("v: {}", v);
-        }
-        if values.len() < 2 {
-            debug!("Failed to get make and model for {}", url);
-            // return HashMap::new();
-        } else {
-            map.insert("make".to_string(), values[0].to_string());
-            map.insert("model".to_string(), values[1].to_string());
-        }
-    }
-
-    if document.select(&KAPARO_SELECTOR).count() > 0 {
-        map.insert("sold".to_string(), "true".to_string());
-    } else {
-        map.insert("sold".to_string(), "false".to_string());
-    }
-
-    if document.select(&TOP_SELECTOR).count() > 0 {
-        map.insert("top".to_string(), "true".to_string());
-    } else if document.select(&VIP_SELECTOR).count() > 0 {
-        map.insert("vip".to_string(), "true".to_string());
-    } else {
-        map.insert("top".to_string(), "false".to_string());
-        map.insert("vip".to_string(), "false".to_string());
-    }
-
-    for element in document.select(&DILAR_SELECTOR) {
-        let txt = element.text().collect::<Vec<_>>().join("_");
-        let lines = txt.lines();
-
-        for l in lines {
-            if l.contains('_') {
-                let v = l.split('_').collect::<Vec<&str>>();
-                if v.len() >= 3 {
-                    if ENGINE_TXT == v[1] {
-                        map.insert("engine".to_string(), v[2].to_string());
-                    }
-                    if GEARBOX_TXT == v[1] {
-                        map.insert("gearbox".to_string(), v[2].to_string());
-                    }
-
-                    if v[1].contains(POWER_TXT) {
-                        map.insert("power".to_string(), extract_integers(v[2])[0].to_string());
-                    }
-
-                    if v[1].contains(MILLAGE_TXT) {
-                        if let Some(numeric_part) = v[2].split_whitespace().next() {
-                            // Remove any non-numeric characters and convert to an integer
-                            if let Ok(numeric_value) = numeric_part
-                                .chars()
-                                .filter(|&c| c.is_numeric())
-                                .collect::<String>()
-                                .parse::<i32>()
-                            {
-                                map.insert("millage".to_string(), numeric_value.to_string());
-                            } else {
-                                map.insert("millage".to_string(), "0".to_string());
-                            }
-                        } else {
-                            error!("Millage not found for {}", url);
-                        }
-                    }
-
-                    if v[1].contains(YEAR_TXT) {
-                        debug!("v[2]: {}", v[2]);
-
-                        // Remove any non-numeric characters and convert to an integer
-                        if let Ok(numeric_value) = v[2]
-                            .chars()
-                            .filter(|&c| c.is_numeric())
-                            .collect::<String>()
-                            .parse::<i32>()
-                        {
-                            map.insert("year".to_string(), numeric_value.to_string());
-                        } else {
-                            map.insert("year".to_string(), "0".to_string());
-                        }
-                    }
-                }
-            }
+impl fmt::Display for DetailsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DetailsError::NoId(url) => write!(f, "No advert id in url: {}", url),
+            DetailsError::NotFound => write!(f, "Not found"),
+            DetailsError::Fetch(e) => write!(f, "Fetch failed: {}", e),
+            DetailsError::Parse(e) => write!(f, "Parse failed: {}", e),
         }
     }
-    debug!("--> map: {:?}", map);
-    for element in document.select(&ADV_ACT_SELECTOR) {
-        let txt = element.text().collect::<Vec<_>>().join(" ");
-        map.insert(
-            "view_count".to_string(),
-            extract_integers(&txt)[0].to_string(),
-        );
-    }
+}
 
-    for element in document.select(&DETAILS_PRICE_SELECTOR) {
-        let txt = element.text().collect::<Vec<_>>().join("");
-        let (price, currency) = process_price(txt);
-        map.insert("currency".to_string(), currency.to_string());
-        map.insert("price".to_string(), price.to_string());
-    }
+impl std::error::Error for DetailsError {}
 
-    let divs = document.select(&DIV_MARGIN_SELECTOR);
-    let mut extras = vec![];
-    for div in divs {
-        extras.push(
-            div.text()
-                .collect::<String>()
-                .replace('•', "")
-                .trim()
-                .to_string(),
-        );
+/// Fetches the details page of the advert and parses it.
+pub async fn get_details(fetcher: &dyn Fetcher, url: &str) -> Result<DetailsPage, DetailsError> {
+    debug!("Processing details {}", url);
+    let id = get_id_from_url(url.to_string()).ok_or(DetailsError::NoId(url.to_string()))?;
+    let page = fetcher.fetch(url).await.map_err(DetailsError::Fetch)?;
+    if page.status == 404 {
+        return Err(DetailsError::NotFound);
     }
-    if !&extras.is_empty() {
-        map.insert(
-            "equipment".to_string(),
-            get_equipment_as_u64(extras).to_string(),
-        );
+    match parse_details(&id, &page.html) {
+        Ok(details) => Ok(details),
+        Err(ParseError::NotFound) => Err(DetailsError::NotFound),
+        Err(e) => Err(DetailsError::Parse(e)),
     }
-    map
 }
 
-pub async fn get_links(url: &str) -> Vec<String> {
-    let html = get_pages_async(url).await.unwrap();
+pub async fn get_links(fetcher: &dyn Fetcher, url: &str) -> Vec<String> {
+    let html = match fetcher.fetch(url).await {
+        Ok(page) => page.html,
+        Err(e) => {
+            error!("Error getting links {}", e);
+            return vec![];
+        }
+    };
     let document = Html::parse_document(&html);
     let mut links = vec![];
-    for element in document.select(&TABLERESET_SELECTOR) {
+    for element in document.select(&SELECTORS.listing.row) {
         if let Some(url) = get_url(&element) {
             links.push(format!("https:{}", url));
         }
@@ -216,15 +75,57 @@ pub async fn get_links(url: &str) -> Vec<String> {
     links
 }
 
+/// Fetches a listing page and parses its rows.
+pub async fn get_listings(fetcher: &dyn Fetcher, url: &str) -> Vec<ListingRecord> {
+    match fetcher.fetch(url).await {
+        Ok(page) => listing_records(&page.html),
+        Err(e) => {
+            error!("Error getting listings {}", e);
+            vec![]
+        }
+    }
+}
+
+/// The rows of a listing page, without the ones that have no advert id.
+pub fn listing_records(html: &str) -> Vec<ListingRecord> {
+    let document = Html::parse_document(html);
+    document
+        .select(&SELECTORS.listing.row)
+        .filter_map(|element| listing_record(&element))
+        .collect()
+}
+
+fn listing_record(element: &ElementRef) -> Option<ListingRecord> {
+    let id = get_id_from_url(get_url(element)?)?;
+    let (make, model) = make_and_mode(element, HashMap::new()).unwrap_or_default();
+    let price = element
+        .select(&SELECTORS.listing.price)
+        .next()
+        .map(|price| parse_price(&price.text().collect::<String>()))
+        .unwrap_or_default();
+    let promoted = is_top_or_vip(element);
+    let (millage, year) = get_milllage_and_year(element, promoted);
+    Some(ListingRecord {
+        id,
+        make,
+        model,
+        currency: price.currency,
+        price: price.amount,
+        millage,
+        year: u16::try_from(year).unwrap_or_default(),
+        promoted,
+        reserved: is_sold(element),
+        created_on: CREATED_ON.to_string(),
+    })
+}
+
 pub fn get_header_data(html: &str) -> Result<String, Box<dyn std::error::Error>> {
     let fragment = Html::parse_document(html);
     let description = fragment
-        .select(&META_DESC_SELECTOR)
+        .select(&SELECTORS.search.description)
         .next()
-        .unwrap()
-        .value()
-        .attr("content")
-        .unwrap()
+        .and_then(|meta| meta.value().attr("content"))
+        .ok_or("Missing meta description, not a search page")?
         .to_string();
     Ok(description)
 }
@@ -232,7 +133,7 @@ pub fn get_header_data(html: &str) -> Result<String, Box<dyn std::error::Error>>
 pub fn get_metadata_links(html: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
     let document = Html::parse_document(html);
     let mut links = vec![];
-    for element in document.select(&PAGE_NUMBERS_SELECTOR) {
+    for element in document.select(&SELECTORS.search.page_numbers) {
         let txt = element.value().attr("href").unwrap_or("");
         if links.contains(&txt.to_string()) {
             continue;
@@ -243,29 +144,8 @@ pub fn get_metadata_links(html: &str) -> Result<Vec<String>, Box<dyn std::error:
     Ok(links)
 }
 
-fn process_price(text: String) -> (u32, Currency) {
-    let contains_numeric = text.chars().any(|c| c.is_numeric());
-    if !contains_numeric {
-        return (0, Currency::BGN);
-    }
-    let v = text.replace(' ', "");
-    let v1 = v.replace("&nbsp;", "");
-    let (price_str, currency) = if v1.contains("USD") {
-        (v1.trim_end_matches("USD"), Currency::USD)
-    } else if v1.contains("EUR") {
-        (v1.trim_end_matches("EUR"), Currency::EUR)
-    } else {
-        (v1.trim_end_matches("лв."), Currency::BGN)
-    };
-    let price = match price_str.parse::<f32>() {
-        Ok(p) => p.floor() as u32,
-        Err(_) => return (0, Currency::BGN), // return None if the string cannot be parsed as u32
-    };
-    (price, currency)
-}
-
 fn get_url(element: &ElementRef) -> Option<String> {
-    match element.select(&TOP_MMM_SELECTOR).next() {
+    match element.select(&SELECTORS.listing.link).next() {
         Some(e) => {
             let href = e.value().attr("href").unwrap();
             Some(href.to_owned())
@@ -274,49 +154,37 @@ fn get_url(element: &ElementRef) -> Option<String> {
     }
 }
 
-fn get_id_from_url(url: String) -> Option<String> {
+pub fn get_id_from_url(url: String) -> Option<String> {
     let id = url
         .split('&')
         .find(|s| s.starts_with("adv="))?
         .split('=')
-        .last()?;
+        .next_back()?;
     Some(id.to_owned())
 }
 
-pub async fn get_pages_async(url: &str) -> Result<String, Box<dyn std::error::Error>> {
-    let client = reqwest::Client::builder()
-        .user_agent(BROWSER_USER_AGENT)
-        .build()?;
-    let body: Vec<u8> = client.get(url).send().await?.bytes().await?.to_vec();
-    debug!("body: {}", body.len());
-    // Decode the byte array using the Windows-1251 encoding
-    let (html, _, _) = WINDOWS_1251.decode(&body);
-    // Convert the decoded text to UTF-8
-    let utf8_html = UTF_8.encode(&html).0;
-    let response = String::from_utf8_lossy(&utf8_html);
-    debug!("response: {}", response.len());
-    Ok(response.to_string())
+pub async fn get_pages_async(
+    fetcher: &dyn Fetcher,
+    url: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let page = fetcher.fetch(url).await?;
+    Ok(page.html)
 }
 
 pub fn get_pages(url: &str) -> Result<String, Box<dyn std::error::Error>> {
-    let client = reqwest::blocking::Client::builder()
-        .user_agent(BROWSER_USER_AGENT)
-        .build()?;
-    let https_url = format!("https:{}", url);
-    let body: Vec<u8> = client.get(https_url).send()?.bytes().unwrap().to_vec();
-    // Decode the byte array using the Windows-1251 encoding
-    let (html, _, _) = WINDOWS_1251.decode(&body);
-    // Convert the decoded text to UTF-8
-    let utf8_html = UTF_8.encode(&html).0;
-    let response = String::from_utf8_lossy(&utf8_html);
-    Ok(response.to_string())
+    let response = BLOCKING_CLIENT.get(normalize_url(url)).send()?;
+    let content_type = content_type(response.headers());
+    let body: Vec<u8> = response.bytes()?.to_vec();
+    let (html, encoding) = charset::decode(content_type.as_deref(), &body);
+    debug!("Decoded {} as {}", url, encoding);
+    Ok(html)
 }
 
 pub fn slink(html: &str) -> String {
     let document = Html::parse_document(html);
     let mut result = "".to_string();
 
-    for element in document.select(&INPUT_TYPE_HIDDEN) {
+    for element in document.select(&SELECTORS.search.slink) {
         if let Some(txt) = element.value().attr("value") {
             result = txt.to_string();
             break; // Exit the loop once a value is found
@@ -330,8 +198,7 @@ pub fn make_and_mode(
     element: &ElementRef,
     models: HashMap<&str, Vec<&str>>,
 ) -> Option<(String, String)> {
-    let selector = Selector::parse("td.valgtop a.mmm").unwrap();
-    for e in element.select(&selector) {
+    for e in element.select(&SELECTORS.listing.link) {
         let inner_html = e.inner_html();
         let strings = inner_html.split_ascii_whitespace().collect::<Vec<&str>>();
         if strings.is_empty() || strings.len() < 2 {
@@ -346,46 +213,28 @@ pub fn make_and_mode(
 }
 
 pub fn is_top_or_vip(element: &ElementRef) -> bool {
-    let top = vec!["top", "vip"];
-    for value in top {
-        let filter = format!(r#"img[alt="{}"][class="noborder"]"#, value);
-        let selector = Selector::parse(&filter).unwrap();
-        let img_element_exists = element.select(&selector).next().is_some();
-        if img_element_exists {
-            return true;
-        }
-    }
-
-    false
+    element.select(&SELECTORS.listing.promoted).next().is_some()
 }
 
 pub fn is_sold(element: &ElementRef) -> bool {
-    let filter = r#"img"#;
-    let selector = Selector::parse(filter).unwrap();
-    let images = element.select(&selector);
-
-    for img_element in images {
-        if let Some(src) = img_element.value().attr("src") {
-            if src.contains("kaparirano.svg") {
-                return true;
-            }
-        }
-    }
-
-    false
+    element.select(&SELECTORS.listing.sold).next().is_some()
 }
 
+/// The row reads "дата на произв. - юни 2010 г., пробег - 232167 км", so the
+/// year comes first.
 pub fn get_milllage_and_year(element: &ElementRef, is_promoted: bool) -> (u32, u32) {
-    let filter = match is_promoted {
-        true => r#"td[colspan="3"]"#,
-        false => r#"td[colspan="4"]"#,
+    let selector = match is_promoted {
+        true => &SELECTORS.listing.promoted_summary,
+        false => &SELECTORS.listing.summary,
     };
 
-    let selector = Selector::parse(filter).unwrap();
-    let mut txt = element.select(&selector).next().unwrap().inner_html();
-    txt = extract_ascii_latin(&txt);
+    let txt = match element.select(selector).next() {
+        Some(td) => extract_ascii_latin(&td.inner_html()),
+        None => return (0, 0),
+    };
 
-    extract_numbers(&txt)
+    let (year, millage) = extract_numbers(&txt);
+    (millage, year)
 }
 
 pub fn extract_numbers(input: &str) -> (u32, u32) {
@@ -423,20 +272,82 @@ pub fn extract_numbers(input: &str) -> (u32, u32) {
 mod scrape_tests {
     use log::info;
 
-    use crate::utils::helpers::configure_log4rs;
+    use crate::{
+        http::mock::{fixture, MockFetcher},
+        utils::helpers::configure_log4rs,
+    };
+
+    use crate::model::enums::Currency;
 
-    use super::details2map;
+    use super::{extract_numbers, get_details, get_links, listing_records, DetailsError};
 
     #[tokio::test]
     async fn test_get_details() {
         let id = "11695819071264345";
         let url = format!(
-            "//www.mobile.bg/pcgi/mobile.cgi?act=4&adv={}&slink=u976ho",
+            "https://www.mobile.bg/pcgi/mobile.cgi?act=4&adv={}&slink=u976ho",
             id
         );
         configure_log4rs("config/loggers/dev_log4rs.yml");
-        let details = details2map(&url).await;
-        assert_eq!(details.get("id").unwrap(), id);
+        let fetcher = MockFetcher::with_fixtures().await;
+        let details = get_details(&fetcher, &url).await.unwrap();
         info!("details: {:?}", details);
+        assert_eq!(details.id, id);
+        assert_eq!(details.make, "Audi");
+        assert_eq!(details.model, "A4");
+        assert_eq!(details.price.amount, 18400);
+    }
+
+    #[tokio::test]
+    async fn test_get_details_not_found() {
+        let id = "11695819071264345";
+        let url = format!(
+            "https://www.mobile.bg/pcgi/mobile.cgi?act=4&adv={}&slink=u976ho",
+            id
+        );
+        let mut fetcher = MockFetcher::with_fixtures().await;
+        fetcher
+            .mock_details(Some(id), &fixture("not_found.html"))
+            .await;
+        let details = get_details(&fetcher, &url).await;
+        assert!(matches!(details, Err(DetailsError::NotFound)));
+        let details = get_details(&fetcher, "https://www.mobile.bg/pcgi/mobile.cgi?act=4").await;
+        assert!(matches!(details, Err(DetailsError::NoId(_))));
+    }
+
+    #[tokio::test]
+    async fn test_get_links() {
+        let fetcher = MockFetcher::with_fixtures().await;
+        let url = "https://www.mobile.bg/pcgi/mobile.cgi?act=3&topmenu=1&rub=1&f1=1&slink=uafcc1";
+        let links = get_links(&fetcher, url).await;
+        assert_eq!(
+            links,
+            vec![
+                "https://www.mobile.bg/pcgi/mobile.cgi?act=4&adv=11695215675724995&slink=uafcc1",
+                "https://www.mobile.bg/pcgi/mobile.cgi?act=4&adv=11698329457005120&slink=uafcc1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_listing_records() {
+        let records = listing_records(&fixture("listing.html"));
+        assert_eq!(2, records.len());
+        let audi = &records[0];
+        assert_eq!("11695215675724995", audi.id);
+        assert_eq!(("Audi", "A4"), (audi.make.as_str(), audi.model.as_str()));
+        assert_eq!((18400, Currency::BGN), (audi.price, audi.currency));
+        assert_eq!((232167, 2010), (audi.millage, audi.year));
+        assert!(!audi.promoted && !audi.reserved);
+        let promoted = &records[1];
+        assert_eq!("A6", promoted.model);
+        assert_eq!((189000, 2013), (promoted.millage, promoted.year));
+        assert!(promoted.promoted);
+        assert_eq!((0, 0), extract_numbers("no numbers"));
+
+        // A year that does not fit is dropped, not wrapped around.
+        let html = fixture("listing.html").replace("юни 2010 г.", "юни 202010 г.");
+        let records = listing_records(&html);
+        assert_eq!((232167, 0), (records[0].millage, records[0].year));
     }
 }
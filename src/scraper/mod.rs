@@ -1 +1,6 @@
+pub mod dates;
+pub mod details;
+pub mod health;
 pub mod mobile_bg;
+pub mod price;
+pub mod summary;
@@ -0,0 +1,98 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::model::enums::Currency;
+
+pub const NEGOTIABLE_TXT: &str = "по договаряне";
+pub const ON_REQUEST_TXT: &str = "цена при запитване";
+const VAT_TXT: &str = "ддс";
+const VAT_EXCLUDED_TXT: [&str; 2] = ["без ддс", "освободен"];
+
+lazy_static! {
+    static ref AMOUNT_REGEX: Regex =
+        Regex::new(r"(\d[\d ]*)(?:[.,](\d+))?\s*(лв\.?|BGN|EUR|€|USD|\$)?").unwrap();
+}
+
+/// A price as shown on a listing row or on a details page.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Price {
+    pub amount: u32,
+    pub currency: Currency,
+    /// The second amount of the dual BGN/EUR display.
+    pub secondary: Option<(u32, Currency)>,
+    pub negotiable: bool,
+    pub on_request: bool,
+    /// `None` when the price says nothing about VAT.
+    pub vat_included: Option<bool>,
+}
+
+/// Reads every amount of the text, the first one being the price. An amount
+/// without a currency is in BGN. The fraction is dropped.
+pub fn parse_price(text: &str) -> Price {
+    let text = text.replace("&nbsp;", " ").replace('\u{a0}', " ");
+    let lower = text.to_lowercase();
+    let vat_included = if VAT_EXCLUDED_TXT.iter().any(|t| lower.contains(t)) {
+        Some(false)
+    } else if lower.contains(VAT_TXT) {
+        Some(true)
+    } else {
+        None
+    };
+    let mut amounts = AMOUNT_REGEX.captures_iter(&text).filter_map(|captures| {
+        let amount = captures[1].replace(' ', "").parse::<u32>().ok()?;
+        let currency = captures.get(3).map_or("", |c| c.as_str());
+        Some((amount, currency_of(currency)))
+    });
+    let (amount, currency) = amounts.next().unwrap_or((0, Currency::BGN));
+    Price {
+        amount,
+        currency,
+        secondary: amounts.next(),
+        negotiable: lower.contains(NEGOTIABLE_TXT),
+        on_request: lower.contains(ON_REQUEST_TXT),
+        vat_included,
+    }
+}
+
+fn currency_of(symbol: &str) -> Currency {
+    match symbol {
+        "EUR" | "€" => Currency::EUR,
+        "USD" | "$" => Currency::USD,
+        _ => Currency::BGN,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::enums::Currency;
+
+    use super::{parse_price, Price};
+
+    #[test]
+    fn test_parse_price() {
+        assert_eq!(
+            Price {
+                amount: 18400,
+                ..Default::default()
+            },
+            parse_price("18 400 лв.")
+        );
+        assert_eq!((12500, Currency::EUR), {
+            let price = parse_price("12&nbsp;500 EUR");
+            (price.amount, price.currency)
+        });
+        assert_eq!(16777217, parse_price("16 777 217.99 USD").amount);
+
+        let price = parse_price("9 407 €\n18 399,45 лв. с ДДС");
+        assert_eq!((9407, Currency::EUR), (price.amount, price.currency));
+        assert_eq!(Some((18399, Currency::BGN)), price.secondary);
+        assert_eq!(Some(true), price.vat_included);
+        assert_eq!(Some(false), parse_price("20 000 лв. без ДДС").vat_included);
+
+        let price = parse_price("По договаряне");
+        assert!(price.negotiable);
+        assert_eq!(0, price.amount);
+        assert!(parse_price("Цена при запитване").on_request);
+        assert_eq!(Price::default(), parse_price(""));
+    }
+}
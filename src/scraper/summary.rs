@@ -0,0 +1,81 @@
+use std::fmt;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Serialize;
+
+lazy_static! {
+    /// A number, with or without spaces between the thousands.
+    static ref NUMBER_REGEX: Regex = Regex::new(r"\d{1,3}(?:[ \x{a0}]\d{3})+|\d+").unwrap();
+}
+
+/// The price range and the number of adverts of a search, as given by the
+/// meta description of the first results page.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize)]
+pub struct SearchSummary {
+    pub min_price: u32,
+    pub max_price: u32,
+    pub total_number: u32,
+}
+
+/// The description does not read "... от <min> лв. до <max> лв. Общо <n> обяви.".
+#[derive(Debug, Clone, PartialEq)]
+pub struct SummaryError(pub String);
+
+impl fmt::Display for SummaryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unexpected search summary '{}'", self.0)
+    }
+}
+
+impl std::error::Error for SummaryError {}
+
+/// Reads the first three numbers of the description as the min price, the
+/// max price and the number of adverts.
+pub fn parse_summary(description: &str) -> Result<SearchSummary, SummaryError> {
+    let description = description.replace("&nbsp;", " ");
+    let numbers = NUMBER_REGEX
+        .find_iter(&description)
+        .map(|number| number.as_str().replace([' ', '\u{a0}'], "").parse::<u32>())
+        .take(3)
+        .collect::<Result<Vec<u32>, _>>()
+        .map_err(|_| SummaryError(description.clone()))?;
+    match numbers[..] {
+        [min_price, max_price, total_number] if min_price <= max_price => Ok(SearchSummary {
+            min_price,
+            max_price,
+            total_number,
+        }),
+        _ => Err(SummaryError(description)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_summary, SearchSummary};
+
+    #[test]
+    fn test_parse_summary() {
+        let summary = SearchSummary {
+            min_price: 1100,
+            max_price: 25000,
+            total_number: 2,
+        };
+        assert_eq!(
+            Ok(summary),
+            parse_summary(
+                "Обяви за продажба на автомобили: цени от 1 100 лв. до 25 000 лв. Общо 2 обяви."
+            )
+        );
+        assert_eq!(
+            Ok(SearchSummary {
+                total_number: 12345,
+                ..summary
+            }),
+            parse_summary("Цени от 1\u{a0}100 лв. до 25&nbsp;000 лв.  Общо 12 345 обяви")
+        );
+        assert!(parse_summary("Общо 0 обяви.").is_err());
+        assert!(parse_summary("").is_err());
+        assert!(parse_summary("цени от 25 000 лв. до 1 100 лв. Общо 2 обяви.").is_err());
+    }
+}
@@ -0,0 +1,140 @@
+use std::error::Error;
+
+use log::{error, info};
+
+use crate::model::enums::Dealer;
+
+use super::{
+    price_backfill::{column, read_rows, rewrite_rows, snapshot_files, with_value},
+    run_status::RunStatus,
+};
+
+/// The number of rows of the file with the old `true`/`false` dealer flag.
+pub fn legacy_flags(file_name: &str) -> Result<usize, Box<dyn Error>> {
+    let mut reader = csv::Reader::from_path(file_name)?;
+    let Some(column) = reader.headers()?.iter().position(|h| h == "dealer") else {
+        return Ok(0);
+    };
+    let mut count = 0;
+    for row in reader.records() {
+        if matches!(row?.get(column), Some("true") | Some("false")) {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// Rewrites the snapshot with `Private` for the old `true` and `Dealer` for
+/// the old `false`, leaving the other columns as they were. Returns the
+/// number of flags flipped.
+pub fn migrate_file(file_name: &str) -> Result<usize, Box<dyn Error>> {
+    if legacy_flags(file_name)? == 0 {
+        return Ok(0);
+    }
+    let (headers, mut rows) = read_rows(file_name)?;
+    let dealer = column(&headers, "dealer", file_name)?;
+    let mut flipped = 0;
+    for row in rows.iter_mut() {
+        if matches!(&row[dealer], "true" | "false") {
+            let flag = row[dealer].parse::<Dealer>()?;
+            *row = with_value(row, dealer, &flag.to_string());
+            flipped += 1;
+        }
+    }
+    rewrite_rows(file_name, &headers, &rows)?;
+    Ok(flipped)
+}
+
+pub fn migrate_dealers(data_dir: &str) -> Result<RunStatus, Box<dyn Error>> {
+    let files = snapshot_files(data_dir)?;
+    let mut failed = 0;
+    for file_name in files.iter() {
+        match migrate_file(file_name) {
+            Ok(flipped) => info!("Flipped {} dealer flags in {}", flipped, file_name),
+            Err(e) => {
+                error!("Failed to migrate {}: {}", file_name, e);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(format!("{} of {} files failed", failed, files.len()).into());
+    }
+    Ok(RunStatus::Completed)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        model::{enums::Dealer, records::MobileRecord},
+        services::file_processor::DataProcessor,
+        utils::helpers::create_empty_csv,
+        writer::persistance::{MobileData, MobileDataWriter},
+    };
+
+    use super::{legacy_flags, migrate_dealers, migrate_file};
+
+    #[test]
+    fn test_migrate_dealers() {
+        let dir = std::env::temp_dir().join("data-scraper-dealers");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let dir = dir.to_str().unwrap().to_string();
+        let snapshot = format!("{}/vehicle-2023-10-03.csv", dir);
+        create_empty_csv::<MobileRecord>(&snapshot).unwrap();
+        let record = |id: &str, dealer: Dealer| MobileRecord {
+            id: id.to_string(),
+            dealer,
+            created_on: "2023-10-03".to_string(),
+            ..Default::default()
+        };
+        MobileData::Payload(vec![
+            record("1", Dealer::PRIVATE),
+            record("2", Dealer::DEALER),
+        ])
+        .write_csv(&snapshot, false)
+        .unwrap();
+        // The old files had `true` for the private sellers.
+        let legacy = fs::read_to_string(&snapshot)
+            .unwrap()
+            .replace(",Private,", ",true,")
+            .replace(",Dealer,", ",false,");
+        fs::write(&snapshot, legacy).unwrap();
+        assert_eq!(2, legacy_flags(&snapshot).unwrap());
+
+        migrate_dealers(&dir).unwrap();
+        assert_eq!(0, legacy_flags(&snapshot).unwrap());
+        let records = DataProcessor::<MobileRecord>::from_files(vec![&snapshot]).get_values();
+        assert_eq!(2, records.len());
+        assert_eq!(Dealer::PRIVATE, records[0].dealer);
+        assert_eq!(Dealer::DEALER, records[1].dealer);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_migrate_legacy_header() {
+        let dir = std::env::temp_dir().join("data-scraper-dealers-legacy");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        // The header of this file has `promoted` and neither `top` nor `vip`.
+        let snapshot = dir.join("vehicle-2023-11-02.csv");
+        let snapshot = snapshot.to_str().unwrap();
+        fs::copy("resources/data/vehicle-2023-11-02.csv", snapshot).unwrap();
+        let before = fs::read_to_string(snapshot).unwrap();
+        let flags = legacy_flags(snapshot).unwrap();
+        assert_eq!(500, flags);
+
+        assert_eq!(flags, migrate_file(snapshot).unwrap());
+        let after = fs::read_to_string(snapshot).unwrap();
+        assert_eq!(before.lines().count(), after.lines().count());
+        assert_eq!(0, legacy_flags(snapshot).unwrap());
+        let first = |text: &str| text.lines().nth(1).unwrap().to_string();
+        assert_eq!(
+            first(&before).replace(",true,2023-11-02", ",Private,2023-11-02"),
+            first(&after)
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
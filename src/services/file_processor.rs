@@ -69,7 +69,7 @@ impl<T: Identity + Clone + Header + Debug + DeserializeOwned + Serialize> DataPr
         }
     }
 
-    pub fn new_values(&self, source: &Vec<T>) -> Vec<T> {
+    pub fn new_values(&self, source: &[T]) -> Vec<T> {
         if source.is_empty() {
             return vec![];
         }
@@ -82,7 +82,7 @@ impl<T: Identity + Clone + Header + Debug + DeserializeOwned + Serialize> DataPr
         new_values
     }
 
-    pub fn process(&mut self, source: &Vec<T>, target: Option<&str>) -> Vec<T> {
+    pub fn process(&mut self, source: &[T], target: Option<&str>) -> Vec<T> {
         if source.is_empty() {
             return vec![];
         }
@@ -1,58 +1,190 @@
-use std::{collections::HashSet, error::Error, fmt::Debug, vec};
+use std::{collections::HashSet, error::Error, fmt::Debug, sync::Arc, time::Instant, vec};
 
 use crossbeam_channel::{Receiver, Sender};
 use futures::StreamExt;
 use log::{debug, error, info};
 use reqwest::Url;
-use tokio::{sync::Mutex, task::JoinHandle};
+use tokio::{
+    sync::Semaphore,
+    task::{JoinError, JoinHandle, JoinSet},
+};
 
 use crate::{
+    config::{data_files::DataFiles, run_options::RunOptions},
+    http::{
+        cache::{CacheMode, CachedFetcher, PageCache},
+        circuit_breaker::{CircuitBreaker, CircuitBreakerFetcher},
+        fetcher::{Fetcher, HttpFetcher},
+        images::ImageDownloader,
+        rate_limiter::RateLimitedFetcher,
+        retry::{FetchStats, RetryFetcher},
+        robots::RobotsCache,
+        warc::WarcWriter,
+    },
     model::{
+        description::Description,
         enums::SaleType,
+        error::DataError,
         id_list::IDList,
-        records::MobileRecord,
+        records::{ListingRecord, MobileRecord},
+        review::Review,
         search_metadata::{asearch, asearches, SearchMetadata},
     },
-    scraper::mobile_bg::{details2map, get_links},
+    scraper::{
+        details::DetailsPage,
+        health::{FieldHit, SelectorHealth},
+        mobile_bg::{get_details, get_id_from_url, get_links, get_listings, DetailsError},
+    },
     utils::helpers::{create_empty_csv, crossbeam_utils::to_stream, mobile_search_url},
     writer::persistance::{MobileData, MobileDataWriter},
-    ARCHIVE_FILE_NAME, DELETED_FILE_NAME, DETAILS_URL, FOR_UPDATE_FILE_NAME, INSALE_FILE_NAME,
-    LISTING_URL, METADATA_FILE_NAME, UPDATED_FILE_NAME, UPDATED_VEHICLES_FILE_NAME,
+    CONFIG, DETAILS_URL, LISTING_URL,
 };
-use lazy_static::lazy_static;
 
-use super::file_processor::{self, DataProcessor};
+use super::{
+    file_processor::{self, DataProcessor},
+    run_status::RunStatus,
+};
 pub const FLUSH_SIZE: usize = 400;
-lazy_static! {
-    static ref LISTING_MUTEX: Mutex<()> = Mutex::new(());
-    static ref DETAILS_MUTEX: Mutex<()> = Mutex::new(());
+
+/// Outcome of the details stage of a run.
+#[derive(Debug, Default)]
+pub struct LinkStats {
+    pub processed: usize,
+    pub not_found: Vec<String>,
+    pub failed: Vec<DataError>,
+    /// Values of the saved adverts that could not be read.
+    pub invalid: Vec<DataError>,
+    /// Titles and locations missing from the catalogues.
+    pub unmatched: Vec<Review>,
+    /// Hits and misses of the details selectors.
+    pub health: SelectorHealth,
+    pub descriptions: Vec<Description>,
 }
 
-pub async fn update() -> Result<(), Box<dyn Error>> {
-    if create_empty_csv::<MobileRecord>(&UPDATED_VEHICLES_FILE_NAME).is_err() {
-        error!(
-            "Failed to create file {}",
-            UPDATED_VEHICLES_FILE_NAME.clone()
-        );
+/// Every attempt of the retry fetcher that misses the cache waits for the
+/// circuit breaker and then for the rate limiter. In replay mode the site is
+/// not contacted at all.
+fn live_fetcher(
+    stats: Arc<FetchStats>,
+    breaker: Arc<CircuitBreaker>,
+    robots: Arc<RobotsCache>,
+    options: &RunOptions,
+    files: &DataFiles,
+) -> Arc<dyn Fetcher> {
+    let cache = PageCache::from_hours(&files.cache_dir, CONFIG.get_cache().ttl_hours);
+    let fetcher: Arc<dyn Fetcher> = match options.cache_mode {
+        CacheMode::Replay => Arc::new(CachedFetcher::replay(cache, stats.clone())),
+        mode => {
+            let mut http = HttpFetcher::new();
+            if CONFIG.get_warc().enabled {
+                http = http.with_archive(Arc::new(WarcWriter::new(&files.warc_dir)));
+            }
+            let limited = Arc::new(
+                RateLimitedFetcher::new(Arc::new(http), CONFIG.get_rate_limit().clone())
+                    .with_robots(robots),
+            );
+            let guarded = Arc::new(CircuitBreakerFetcher::new(limited, breaker));
+            if mode == CacheMode::Record {
+                Arc::new(CachedFetcher::record(guarded, cache, stats.clone()))
+            } else {
+                guarded
+            }
+        }
+    };
+    Arc::new(RetryFetcher::new(
+        fetcher,
+        CONFIG.get_retry().clone(),
+        stats,
+    ))
+}
+
+/// robots.txt is fetched straight from the site, with retries. The pages
+/// replayed from the cache were checked when they were recorded.
+fn live_robots(stats: Arc<FetchStats>, options: &RunOptions) -> RobotsCache {
+    let config = CONFIG.get_robots();
+    if !config.enabled || options.cache_mode == CacheMode::Replay {
+        return RobotsCache::disabled();
     }
+    let http = Arc::new(HttpFetcher::new());
+    let retry = RetryFetcher::new(http, CONFIG.get_retry().clone(), stats);
+    RobotsCache::new(Arc::new(retry), &config.user_agent)
+}
+
+/// Runs `task` with the live fetcher and reports how the run ended. A run the
+/// circuit breaker aborted is reported as such even when the parser drifted.
+async fn run<F, Fut>(options: &RunOptions, task: F) -> Result<RunStatus, Box<dyn Error>>
+where
+    F: FnOnce(Arc<dyn Fetcher>, Arc<RobotsCache>, DataFiles) -> Fut,
+    Fut: std::future::Future<Output = Result<SelectorHealth, Box<dyn Error>>>,
+{
+    let stats = Arc::new(FetchStats::default());
+    let breaker = Arc::new(CircuitBreaker::from_config(CONFIG.get_circuit_breaker()));
+    let robots = Arc::new(live_robots(stats.clone(), options));
+    let files = DataFiles::default();
+    let fetcher = live_fetcher(
+        stats.clone(),
+        breaker.clone(),
+        robots.clone(),
+        options,
+        &files,
+    );
+    let result = task(fetcher, robots, files).await;
+    info!("Fetch stats: {}", stats);
+    let health = result?;
+    info!("Selector health:\n{}", health);
+    let drifted = health.drifted(CONFIG.get_parser_health());
+    Ok(match breaker.aborted() {
+        Some(reason) => RunStatus::Aborted(reason),
+        None if !drifted.is_empty() => RunStatus::Drifted(drifted.join(", ")),
+        None => RunStatus::Completed,
+    })
+}
+
+pub async fn update(options: &RunOptions) -> Result<RunStatus, Box<dyn Error>> {
+    run(options, |fetcher, robots, files| async move {
+        update_with(fetcher, robots, &files).await
+    })
+    .await
+}
 
-    if create_empty_csv::<IDList>(&UPDATED_FILE_NAME).is_err() {
-        error!("Failed to create file {:?}", UPDATED_FILE_NAME.clone());
+pub async fn update_with(
+    fetcher: Arc<dyn Fetcher>,
+    robots: Arc<RobotsCache>,
+    files: &DataFiles,
+) -> Result<SelectorHealth, Box<dyn Error>> {
+    if create_empty_csv::<MobileRecord>(&files.updated_vehicles).is_err() {
+        error!("Failed to create file {}", files.updated_vehicles);
     }
 
-    if create_empty_csv::<IDList>(&DELETED_FILE_NAME).is_err() {
-        error!("Failed to create file {:?}", DELETED_FILE_NAME.clone());
+    if create_empty_csv::<IDList>(&files.updated).is_err() {
+        error!("Failed to create file {:?}", files.updated);
     }
 
-    let update_processor = DataProcessor::<IDList>::from_files(vec![&FOR_UPDATE_FILE_NAME]);
+    if create_empty_csv::<IDList>(&files.deleted).is_err() {
+        error!("Failed to create file {:?}", files.deleted);
+    }
+
+    if create_empty_csv::<DataError>(&files.errors).is_err() {
+        error!("Failed to create file {:?}", files.errors);
+    }
+
+    if create_empty_csv::<Review>(&files.review).is_err() {
+        error!("Failed to create file {:?}", files.review);
+    }
+
+    if create_empty_csv::<Description>(&files.descriptions).is_err() {
+        error!("Failed to create file {:?}", files.descriptions);
+    }
+
+    let update_processor = DataProcessor::<IDList>::from_files(vec![&files.for_update]);
     let update_data = update_processor.get_ids().clone();
-    let cloned_ids = update_data.clone();
-    let search_all = asearch(SaleType::INSALE, 1, 9_999_999).await;
+    let search_all = asearch(fetcher.as_ref(), SaleType::INSALE, 1, 9_999_999).await;
     let mut urls = HashSet::new();
 
     let (link_producer, mut link_consumer) = crossbeam::channel::unbounded::<String>();
     let (record_producer, mut record_consumer) = crossbeam::channel::unbounded::<MobileRecord>();
 
+    let producer_robots = robots.clone();
     let producer_task = tokio::spawn(async move {
         for id in update_data {
             let url = format!(
@@ -61,88 +193,223 @@ pub async fn update() -> Result<(), Box<dyn Error>> {
                 id,
                 search_all.slink.clone()
             );
+            if !producer_robots.is_allowed(&url).await {
+                continue;
+            }
             link_producer.send(url.clone()).unwrap();
             urls.insert(url);
         }
         info!("Total number of urls: {}", urls.len());
     });
 
+    let concurrency = CONFIG.get_num_threads();
     let process_links_task = tokio::spawn(async move {
-        process_links(&mut link_consumer, record_producer).await;
+        process_links(fetcher, &mut link_consumer, record_producer, concurrency).await
     });
 
+    let updated_vehicles = files.updated_vehicles.clone();
     let save_to_file_task = tokio::spawn(async move {
-        save(&UPDATED_VEHICLES_FILE_NAME, &mut record_consumer).await;
+        save(&updated_vehicles, &mut record_consumer).await;
     });
 
-    if let (Ok(_), Ok(_), Ok(_)) =
+    let link_stats = if let (Ok(_), Ok(link_stats), Ok(_)) =
         tokio::join!(producer_task, process_links_task, save_to_file_task)
     {
         info!("All tasks completed successfully");
+        link_stats
     } else {
         error!("One or more tasks failed");
         return Err("One or more tasks failed".into());
-    }
+    };
 
-    let updated_processor =
-        DataProcessor::<MobileRecord>::from_files(vec![&UPDATED_VEHICLES_FILE_NAME]);
-    let updated_data = updated_processor.get_ids().clone();
-    let deleted_ids = cloned_ids
-        .difference(&updated_data)
-        .cloned()
-        .collect::<Vec<String>>();
-    save2file(&DELETED_FILE_NAME, deleted_ids);
-    Ok(())
+    // Only the adverts confirmed as removed are deleted. The ones that could
+    // not be fetched are kept for the next update.
+    info!(
+        "Updated: {}, deleted: {}, failed: {}, invalid values: {}, skipped by robots.txt: {}, to review: {}",
+        link_stats.processed,
+        link_stats.not_found.len(),
+        link_stats.failed.len(),
+        link_stats.invalid.len(),
+        robots.skipped().len(),
+        link_stats.unmatched.len()
+    );
+    let deleted_ids = link_stats
+        .not_found
+        .into_iter()
+        .map(IDList::new)
+        .collect::<Vec<IDList>>();
+    save2file(&files.deleted, deleted_ids);
+    save2file(&files.errors, link_stats.failed);
+    save2file(&files.errors, link_stats.invalid);
+    save2file(&files.review, link_stats.unmatched);
+    save2file(&files.descriptions, link_stats.descriptions);
+    Ok(link_stats.health)
+}
+
+pub async fn scrape(options: &RunOptions) -> Result<RunStatus, Box<dyn Error>> {
+    run(options, |fetcher, robots, files| async move {
+        scrape_with(fetcher, robots, &files).await
+    })
+    .await
 }
 
-pub async fn scrape() -> Result<(), Box<dyn Error>> {
-    if create_empty_csv::<MobileRecord>(&INSALE_FILE_NAME).is_err() {
-        error!("Failed to create file {}", INSALE_FILE_NAME.clone());
+pub async fn scrape_with(
+    fetcher: Arc<dyn Fetcher>,
+    robots: Arc<RobotsCache>,
+    files: &DataFiles,
+) -> Result<SelectorHealth, Box<dyn Error>> {
+    if create_empty_csv::<MobileRecord>(&files.insale).is_err() {
+        error!("Failed to create file {}", files.insale);
+    }
+
+    if create_empty_csv::<MobileRecord>(&files.archive).is_err() {
+        error!("Failed to create file {:?}", files.archive);
+    }
+
+    if create_empty_csv::<SearchMetadata>(&files.metadata).is_err() {
+        error!("Failed to create file {:?}", files.metadata);
     }
 
-    if create_empty_csv::<MobileRecord>(&ARCHIVE_FILE_NAME).is_err() {
-        error!("Failed to create file {:?}", ARCHIVE_FILE_NAME.clone());
+    if create_empty_csv::<DataError>(&files.errors).is_err() {
+        error!("Failed to create file {:?}", files.errors);
     }
 
-    if create_empty_csv::<SearchMetadata>(&METADATA_FILE_NAME).is_err() {
-        error!("Failed to create file {:?}", METADATA_FILE_NAME.clone());
+    if create_empty_csv::<Review>(&files.review).is_err() {
+        error!("Failed to create file {:?}", files.review);
+    }
+
+    if create_empty_csv::<Description>(&files.descriptions).is_err() {
+        error!("Failed to create file {:?}", files.descriptions);
     }
 
     let (link_producer, mut link_consumer) = crossbeam::channel::unbounded::<String>();
     let (filter_producer, mut filter_consumer) = crossbeam::channel::unbounded::<String>();
     let (details_producer, mut details_consumer) = crossbeam::channel::unbounded::<MobileRecord>();
+    let search_fetcher = fetcher.clone();
+    let metadata = files.metadata.clone();
+    let search_robots = robots.clone();
     let start = tokio::spawn(async move {
-        start_searches(link_producer).await;
+        start_searches(search_fetcher, search_robots, &metadata, link_producer).await;
     });
+    let filter_robots = robots.clone();
     let filter_task = tokio::spawn(async move {
-        filter_links(&mut link_consumer, filter_producer).await;
+        filter_links(filter_robots, &mut link_consumer, filter_producer).await;
     });
+    let concurrency = CONFIG.get_num_threads();
     let scrape_task = tokio::spawn(async move {
-        process_links(&mut filter_consumer, details_producer).await;
+        process_links(fetcher, &mut filter_consumer, details_producer, concurrency).await
     });
+    let insale = files.insale.clone();
     let save_task = tokio::spawn(async move {
-        save(&INSALE_FILE_NAME, &mut details_consumer).await;
+        save(&insale, &mut details_consumer).await;
     });
 
-    if let (Ok(_), Ok(_), Ok(_), Ok(_)) = tokio::join!(save_task, scrape_task, filter_task, start) {
+    if let (Ok(_), Ok(link_stats), Ok(_), Ok(_)) =
+        tokio::join!(save_task, scrape_task, filter_task, start)
+    {
         info!("All tasks completed successfully");
-        Ok(())
+        info!(
+            "Scraped: {}, not found: {}, failed: {}, invalid values: {}, skipped by robots.txt: {}, to review: {}",
+            link_stats.processed,
+            link_stats.not_found.len(),
+            link_stats.failed.len(),
+            link_stats.invalid.len(),
+            robots.skipped().len(),
+            link_stats.unmatched.len()
+        );
+        save2file(&files.errors, link_stats.failed);
+        save2file(&files.errors, link_stats.invalid);
+        save2file(&files.review, link_stats.unmatched);
+        save2file(&files.descriptions, link_stats.descriptions);
+        if CONFIG.get_images().enabled {
+            download_images(&files.insale, &files.images_dir).await;
+        }
+        Ok(link_stats.health)
     } else {
         error!("One or more tasks failed");
         Err("One or more tasks failed".into())
     }
 }
 
-pub async fn spawn_sequentially(url: String, sender: Sender<String>) -> JoinHandle<()> {
-    let _guard = LISTING_MUTEX.lock().await;
-    tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-    info!("spawn_sequentially");
+pub async fn listing(options: &RunOptions) -> Result<RunStatus, Box<dyn Error>> {
+    run(options, |fetcher, robots, files| async move {
+        listing_with(fetcher, robots, &files).await?;
+        Ok(SelectorHealth::default())
+    })
+    .await
+}
+
+/// A snapshot of the market from the listing pages alone. An advert shown on
+/// more than one page is saved once.
+pub async fn listing_with(
+    fetcher: Arc<dyn Fetcher>,
+    robots: Arc<RobotsCache>,
+    files: &DataFiles,
+) -> Result<(), Box<dyn Error>> {
+    if create_empty_csv::<ListingRecord>(&files.listing).is_err() {
+        error!("Failed to create file {}", files.listing);
+    }
+
+    if create_empty_csv::<SearchMetadata>(&files.metadata).is_err() {
+        error!("Failed to create file {:?}", files.metadata);
+    }
+
+    let (record_producer, mut record_consumer) = crossbeam::channel::unbounded::<ListingRecord>();
+    let listing_file = files.listing.clone();
+    let save_task = tokio::spawn(async move {
+        save(&listing_file, &mut record_consumer).await;
+    });
+
+    let pages = listing_pages(fetcher.as_ref(), &robots, &files.metadata).await;
+    let mut tasks = Vec::new();
+    for url in pages {
+        let fetcher = fetcher.clone();
+        tasks.push(tokio::spawn(async move {
+            get_listings(fetcher.as_ref(), &url).await
+        }));
+    }
+    let mut ids = HashSet::new();
+    for task in tasks {
+        match task.await {
+            Ok(records) => {
+                for record in records {
+                    if ids.insert(record.id.clone()) {
+                        record_producer.send(record).unwrap();
+                    }
+                }
+            }
+            Err(e) => error!("Listing task failed: {}", e),
+        }
+    }
+    drop(record_producer);
+
+    if save_task.await.is_err() {
+        error!("One or more tasks failed");
+        return Err("One or more tasks failed".into());
+    }
+    info!(
+        "Listed: {}, skipped by robots.txt: {}",
+        ids.len(),
+        robots.skipped().len()
+    );
+    Ok(())
+}
+
+pub fn spawn_links(
+    fetcher: Arc<dyn Fetcher>,
+    url: String,
+    sender: Sender<String>,
+) -> JoinHandle<()> {
     tokio::spawn(async move {
-        links(&url, sender).await;
+        links(fetcher.as_ref(), &url, sender).await;
     })
 }
 
-async fn filter_links(consumer: &mut Receiver<String>, producer: Sender<String>) {
+async fn filter_links(
+    robots: Arc<RobotsCache>,
+    consumer: &mut Receiver<String>,
+    producer: Sender<String>,
+) {
     let stream = Box::pin(to_stream(consumer));
     futures::pin_mut!(stream);
     let mut counter = 0;
@@ -155,6 +422,9 @@ async fn filter_links(consumer: &mut Receiver<String>, producer: Sender<String>)
                     continue;
                 }
                 ids.insert(adv_value.1.to_string());
+                if !robots.is_allowed(url.as_str()).await {
+                    continue;
+                }
                 producer.send(url.to_string()).unwrap();
                 counter += 1;
             }
@@ -165,25 +435,51 @@ async fn filter_links(consumer: &mut Receiver<String>, producer: Sender<String>)
     info!("Processed urls: {}", counter);
 }
 
-async fn links(url: &str, sender: Sender<String>) {
-    let links = get_links(url).await;
+async fn links(fetcher: &dyn Fetcher, url: &str, sender: Sender<String>) {
+    let links = get_links(fetcher, url).await;
     for link in links {
         sender.send(link).unwrap();
     }
 }
 
-async fn start_searches(link_producer: Sender<String>) {
+async fn start_searches(
+    fetcher: Arc<dyn Fetcher>,
+    robots: Arc<RobotsCache>,
+    metadata_file: &str,
+    link_producer: Sender<String>,
+) {
+    let pages = listing_pages(fetcher.as_ref(), &robots, metadata_file).await;
+    let mut tasks = Vec::new();
+    for url in pages {
+        let task = spawn_links(fetcher.clone(), url, link_producer.clone());
+        tasks.push(task);
+    }
+
+    for task in tasks {
+        if let Err(e) = task.await {
+            error!("Listing task failed: {}", e);
+        }
+    }
+}
+
+/// Runs the searches, saves their metadata and returns the urls of every
+/// listing page allowed by robots.txt.
+async fn listing_pages(
+    fetcher: &dyn Fetcher,
+    robots: &RobotsCache,
+    metadata_file: &str,
+) -> Vec<String> {
     let mut all = vec![];
-    let searches = asearches().await;
+    let searches = asearches(fetcher).await;
     info!("searches: {:?}", searches.len());
     all.extend(searches.clone());
     for meta in all.iter() {
         info!("{:?}", meta.clone());
     }
     let mut meta_data_processor: DataProcessor<SearchMetadata> =
-        file_processor::DataProcessor::from_files(vec![&METADATA_FILE_NAME]);
+        file_processor::DataProcessor::from_files(vec![metadata_file]);
     meta_data_processor.process(&all, None);
-    let mut tasks = Vec::new();
+    let mut urls = Vec::new();
     let mut counter = 0;
     for search in searches.iter() {
         counter += search.total_number;
@@ -198,51 +494,192 @@ async fn start_searches(link_producer: Sender<String>) {
                 0,
                 0,
             );
-            let task = spawn_sequentially(url, link_producer.clone());
-            tasks.push(task);
+            if !robots.is_allowed(&url).await {
+                continue;
+            }
+            urls.push(url);
         }
     }
     info!("Total number of links: {}", counter);
+    urls
+}
 
-    for task in tasks {
-        task.await;
+enum DetailsOutcome {
+    Processed {
+        /// The values missing from the catalogues.
+        reviews: Vec<Review>,
+        hits: Vec<FieldHit>,
+        description: Option<Description>,
+        invalid: Vec<DataError>,
+    },
+    NotFound(String),
+    /// Carries the field the parser failed on, if any.
+    Failed(DataError, Option<FieldHit>),
+    Skipped,
+}
+
+impl LinkStats {
+    fn add(&mut self, outcome: DetailsOutcome) {
+        match outcome {
+            DetailsOutcome::Processed {
+                reviews,
+                hits,
+                description,
+                invalid,
+            } => {
+                self.processed += 1;
+                self.invalid.extend(invalid);
+                self.unmatched.extend(reviews);
+                self.health.record(&hits);
+                self.descriptions.extend(description);
+            }
+            DetailsOutcome::NotFound(id) => self.not_found.push(id),
+            DetailsOutcome::Failed(e, miss) => {
+                self.failed.push(e);
+                if let Some(miss) = miss {
+                    self.health.record(&[miss]);
+                }
+            }
+            DetailsOutcome::Skipped => {}
+        }
+    }
+
+    fn total(&self) -> usize {
+        self.processed + self.not_found.len() + self.failed.len()
     }
 }
 
-async fn process_links(input: &mut Receiver<String>, output: Sender<MobileRecord>) {
+/// Fetches up to `concurrency` details pages at once. Once the input channel
+/// is closed, the fetches in flight are awaited before returning.
+async fn process_links(
+    fetcher: Arc<dyn Fetcher>,
+    input: &mut Receiver<String>,
+    output: Sender<MobileRecord>,
+    concurrency: usize,
+) -> LinkStats {
     let stream = Box::pin(to_stream(input));
     futures::pin_mut!(stream);
-    let mut counter = 0;
-    let mut urls = HashSet::new();
+    let started = Instant::now();
+    let permits = Arc::new(Semaphore::new(concurrency.max(1)));
+    let mut tasks = JoinSet::new();
+    let mut stats = LinkStats::default();
     while let Some(url) = stream.next().await {
         debug!("url: {}", url.clone());
-        let mut not_found = 0;
-        let data = details2map(url.as_str()).await;
-        if data.is_empty()
-            || !data.contains_key("id")
-            || !data.contains_key("make")
-            || !data.contains_key("engine")
-            || !data.contains_key("gearbox")
-        {
-            urls.insert(url);
-            if urls.len() % 100 == 0 {
-                for u in &urls {
-                    info!("{}", u);
-                }
-                urls.clear();
+        let permit = permits.clone().acquire_owned().await.unwrap();
+        while let Some(done) = tasks.try_join_next() {
+            collect(&mut stats, done);
+        }
+        let fetcher = fetcher.clone();
+        let output = output.clone();
+        tasks.spawn(async move {
+            let outcome = process_link(fetcher.as_ref(), &url, &output).await;
+            drop(permit);
+            outcome
+        });
+    }
+    while let Some(done) = tasks.join_next().await {
+        collect(&mut stats, done);
+    }
+    let elapsed = started.elapsed().as_secs_f64();
+    info!(
+        "Processed urls: {}, not found: {}, failed: {}",
+        stats.processed,
+        stats.not_found.len(),
+        stats.failed.len()
+    );
+    info!(
+        "Details throughput: {} pages in {:.1}s ({:.2} pages/s, concurrency {})",
+        stats.total(),
+        elapsed,
+        stats.total() as f64 / elapsed.max(0.001),
+        concurrency
+    );
+    stats
+}
+
+fn collect(stats: &mut LinkStats, done: Result<DetailsOutcome, JoinError>) {
+    match done {
+        Ok(outcome) => stats.add(outcome),
+        Err(e) => error!("Details task failed: {}", e),
+    }
+}
+
+async fn process_link(
+    fetcher: &dyn Fetcher,
+    url: &str,
+    output: &Sender<MobileRecord>,
+) -> DetailsOutcome {
+    match get_details(fetcher, url).await {
+        Ok(details) => {
+            let reviews = reviews(&details);
+            let hits = details.hits.clone();
+            let invalid = details
+                .errors
+                .iter()
+                .map(|e| DataError::new(&details.id, &e.to_string()))
+                .collect();
+            let description = (!details.description.is_empty())
+                .then(|| Description::new(&details.id, &details.description));
+            output.send(MobileRecord::from(details)).unwrap();
+            DetailsOutcome::Processed {
+                reviews,
+                hits,
+                description,
+                invalid,
             }
-            not_found += 1;
-            info!("Total not found urls: {}", not_found);
-            continue;
         }
-        let record = MobileRecord::from(data);
-        output.send(record).unwrap();
-        //sleep for 100 millis
-        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-        info!("Processed urls: {}", counter);
-        counter += 1;
+        Err(DetailsError::NoId(url)) => {
+            error!("No advert id in url: {}", url);
+            DetailsOutcome::Skipped
+        }
+        Err(DetailsError::NotFound) => DetailsOutcome::NotFound(id_of(url)),
+        Err(e) => {
+            info!("Failed to get the details of {}: {}", url, e);
+            let miss = match &e {
+                DetailsError::Parse(error) => FieldHit::from_error(error),
+                _ => None,
+            };
+            DetailsOutcome::Failed(DataError::new(&id_of(url), &e.to_string()), miss)
+        }
     }
-    info!("Processed urls: {}", counter);
+}
+
+/// Downloads the photos of the adverts saved to `records_file`.
+async fn download_images(records_file: &str, images_dir: &str) {
+    let images = match ImageDownloader::new(CONFIG.get_http(), CONFIG.get_images(), images_dir) {
+        Ok(images) => images,
+        Err(e) => {
+            error!("Failed to create the image downloader: {}", e);
+            return;
+        }
+    };
+    let records = DataProcessor::<MobileRecord>::from_files(vec![records_file]).get_values();
+    images.download_all(&records).await;
+}
+
+fn reviews(details: &DetailsPage) -> Vec<Review> {
+    let mut reviews = vec![];
+    if !details.title_matched {
+        info!("Title not in the make catalogue: {}", details.title);
+        let guess = format!("{} {}", details.make, details.model);
+        reviews.push(Review::new(&details.id, "title", &details.title, &guess));
+    }
+    let place = &details.place;
+    if !place.raw.is_empty() && !place.matched {
+        info!("Location not in the gazetteer: {}", place.raw);
+        let guess = format!("{} {}", place.region, place.city);
+        reviews.push(Review::new(
+            &details.id,
+            "location",
+            &place.raw,
+            guess.trim(),
+        ));
+    }
+    reviews
+}
+
+fn id_of(url: &str) -> String {
+    get_id_from_url(url.to_string()).unwrap_or_default()
 }
 
 fn save2file<T: Clone + Debug + serde::Serialize>(file_name: &str, data: Vec<T>) {
@@ -276,7 +713,10 @@ pub async fn save_active_adverts(file_name: &str, input: &mut Receiver<MobileRec
     save2file(file_name, new_values);
 }
 
-pub async fn save<T: Clone + Debug + serde::Serialize>(file_name: &str, input: &mut Receiver<T>) {
+pub async fn save<T: Clone + Debug + Send + serde::Serialize + 'static>(
+    file_name: &str,
+    input: &mut Receiver<T>,
+) {
     let stream = Box::pin(to_stream(input));
     futures::pin_mut!(stream);
     let mut counter = 0;
@@ -321,32 +761,263 @@ pub async fn print_stream(rx: &mut Receiver<String>) {
 #[cfg(test)]
 mod node_tests {
 
+    use std::{
+        fs,
+        path::PathBuf,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
     use crate::{
-        services::mobile_bg_scraper::start_searches,
-        utils::helpers::{configure_log4rs, crossbeam_utils::to_stream},
+        config::{app_config::RetryConfig, data_files::DataFiles},
+        http::{
+            fetcher::{FetchError, Fetcher, Page},
+            mock::{fixture, MockFetcher},
+            retry::{FetchStats, RetryFetcher},
+            robots::RobotsCache,
+        },
+        model::{
+            description::Description,
+            error::DataError,
+            id_list::IDList,
+            records::{ListingRecord, MobileRecord},
+            review::Review,
+            search_metadata::SearchMetadata,
+        },
+        services::{
+            file_processor::DataProcessor,
+            mobile_bg_scraper::{
+                listing_with, process_links, scrape_with, start_searches, update_with,
+            },
+        },
+        utils::helpers::{configure_log4rs, create_empty_csv, crossbeam_utils::to_stream},
+        writer::persistance::{MobileData, MobileDataWriter},
     };
     use crossbeam_channel::Receiver;
-    use futures::StreamExt;
+    use futures::{future::BoxFuture, StreamExt};
     use log::info;
+    use mockito::Matcher;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("data-scraper-{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Counts the fetches in flight.
+    struct CountingFetcher {
+        inner: MockFetcher,
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+    }
+
+    impl Fetcher for CountingFetcher {
+        fn fetch<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<Page, FetchError>> {
+            Box::pin(async move {
+                let current = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                self.max_in_flight.fetch_max(current, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                let page = self.inner.fetch(url).await;
+                self.in_flight.fetch_sub(1, Ordering::SeqCst);
+                page
+            })
+        }
+    }
 
     #[tokio::test]
     async fn ping_pong_test() {}
 
+    #[tokio::test]
+    async fn test_process_links_concurrency() {
+        let fetcher = Arc::new(CountingFetcher {
+            inner: MockFetcher::with_fixtures().await,
+            in_flight: AtomicUsize::new(0),
+            max_in_flight: AtomicUsize::new(0),
+        });
+        let (link_producer, mut link_consumer) = crossbeam::channel::unbounded::<String>();
+        for id in 0..20 {
+            link_producer
+                .send(format!(
+                    "https://www.mobile.bg/pcgi/mobile.cgi?act=4&adv={}&slink=uafcc1",
+                    id
+                ))
+                .unwrap();
+        }
+        drop(link_producer);
+        let (record_producer, record_consumer) = crossbeam::channel::unbounded::<MobileRecord>();
+        let stats = process_links(fetcher.clone(), &mut link_consumer, record_producer, 4).await;
+        assert_eq!(20, stats.processed);
+        assert_eq!(20, record_consumer.try_iter().count());
+        let max_in_flight = fetcher.max_in_flight.load(Ordering::SeqCst);
+        assert!((2..=4).contains(&max_in_flight));
+    }
+
     #[tokio::test]
     async fn test_searches() {
         configure_log4rs("config/loggers/dev_log4rs.yml");
+        let dir = test_dir("searches");
+        let metadata = dir.join("meta_data.csv").to_str().unwrap().to_string();
+        create_empty_csv::<SearchMetadata>(&metadata).unwrap();
+        let fetcher = Arc::new(MockFetcher::with_fixtures().await);
+        let robots = Arc::new(RobotsCache::new(fetcher.clone(), "data-scraper"));
         let (tx, mut rx) = crossbeam::channel::unbounded::<String>();
         let task = tokio::spawn(async move {
-            start_searches(tx).await;
+            start_searches(fetcher, robots, &metadata, tx).await;
         });
         task.await.unwrap();
-        print(&mut rx).await;
+        let links = print(&mut rx).await;
+        assert_eq!(14, links.len());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_scrape() {
+        configure_log4rs("config/loggers/dev_log4rs.yml");
+        let dir = test_dir("scrape");
+        let files = DataFiles::in_dir(dir.to_str().unwrap());
+        let mut fetcher = MockFetcher::with_fixtures().await;
+        let unknown = fixture("details.html")
+            .replace("Audi A4 2.0 TDI", "Trabant 601 S")
+            .replace("<div class=\"phone\">0897801432</div>", "")
+            .replace("юни 2010 г.", "32015");
+        fetcher
+            .mock_details(Some("11698329457005120"), &unknown)
+            .await;
+        let fetcher = Arc::new(fetcher);
+        let robots = Arc::new(RobotsCache::new(fetcher.clone(), "data-scraper"));
+        let health = scrape_with(fetcher, robots, &files).await.unwrap();
+        assert_eq!(Some(0.5), health.hit_rate("phone"));
+        assert_eq!(Some(1.0), health.hit_rate("price"));
+        let processor = DataProcessor::<MobileRecord>::from_files(vec![&files.insale]);
+        let records = processor.get_values();
+        assert_eq!(2, records.len());
+        assert!(records.iter().all(|r| r.price == 18400));
+        assert!(processor.get_ids().contains("11695215675724995"));
+        assert!(processor.get_ids().contains("11698329457005120"));
+        assert!(records.iter().all(|r| r.image_count == 2));
+        let descriptions =
+            DataProcessor::<Description>::from_files(vec![&files.descriptions]).get_values();
+        assert_eq!(2, descriptions.len());
+        assert!(descriptions[0]
+            .text
+            .starts_with("Колата е в отлично състояние."));
+        // The unreadable production date is an error, the advert is kept.
+        let errors = DataProcessor::<DataError>::from_files(vec![&files.errors]).get_values();
+        assert_eq!(1, errors.len());
+        assert_eq!("11698329457005120", errors[0].id);
+        assert!(errors[0].error.contains("'32015'"));
+        let review = DataProcessor::<Review>::from_files(vec![&files.review]).get_values();
+        assert_eq!(1, review.len());
+        assert_eq!("11698329457005120", review[0].id);
+        assert_eq!(
+            ("title", "Trabant 601 S"),
+            (review[0].field.as_str(), review[0].value.as_str())
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_scrape_robots() {
+        configure_log4rs("config/loggers/dev_log4rs.yml");
+        let dir = test_dir("scrape-robots");
+        let files = DataFiles::in_dir(dir.to_str().unwrap());
+        let mut fetcher = MockFetcher::with_fixtures().await;
+        let disallowed = "/pcgi/mobile.cgi?act=4&adv=11698329457005120";
+        fetcher
+            .mock_robots(200, &format!("User-agent: *\nDisallow: {}\n", disallowed))
+            .await;
+        let fetcher = Arc::new(fetcher);
+        let robots = Arc::new(RobotsCache::new(fetcher.clone(), "data-scraper"));
+        scrape_with(fetcher, robots.clone(), &files).await.unwrap();
+        let processor = DataProcessor::<MobileRecord>::from_files(vec![&files.insale]);
+        assert_eq!(1, processor.get_values().len());
+        assert!(processor.get_ids().contains("11695215675724995"));
+        let skipped = robots.skipped();
+        assert_eq!(1, skipped.len());
+        assert!(skipped[0].contains(disallowed));
+        fs::remove_dir_all(dir).unwrap();
     }
-    async fn print(rx: &mut Receiver<String>) {
+
+    #[tokio::test]
+    async fn test_listing() {
+        configure_log4rs("config/loggers/dev_log4rs.yml");
+        let dir = test_dir("listing");
+        let files = DataFiles::in_dir(dir.to_str().unwrap());
+        let fetcher = Arc::new(MockFetcher::with_fixtures().await);
+        let robots = Arc::new(RobotsCache::new(fetcher.clone(), "data-scraper"));
+        listing_with(fetcher, robots, &files).await.unwrap();
+        let processor = DataProcessor::<ListingRecord>::from_files(vec![&files.listing]);
+        let records = processor.get_values();
+        assert_eq!(2, records.len());
+        assert!(records.iter().all(|r| r.make == "Audi"));
+        assert!(processor.get_ids().contains("11698329457005120"));
+        assert!(!fs::read_to_string(&files.metadata).unwrap().is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_update() {
+        configure_log4rs("config/loggers/dev_log4rs.yml");
+        let dir = test_dir("update");
+        let files = DataFiles::in_dir(dir.to_str().unwrap());
+        create_empty_csv::<IDList>(&files.for_update).unwrap();
+        let ids = vec![
+            IDList::new("1001".to_string()),
+            IDList::new("1002".to_string()),
+            IDList::new("1003".to_string()),
+        ];
+        MobileData::Payload(ids)
+            .write_csv(&files.for_update, false)
+            .unwrap();
+        let mut fetcher = MockFetcher::new().await;
+        fetcher
+            .mock_failures(Matcher::UrlEncoded("adv".into(), "1003".into()), 503, 10)
+            .await;
+        fetcher.add_fixtures().await;
+        fetcher
+            .mock_details(Some("1002"), &fixture("not_found.html"))
+            .await;
+        let stats = Arc::new(FetchStats::default());
+        let retry = RetryConfig {
+            max_attempts: 2,
+            initial_backoff_millis: 1,
+            max_backoff_millis: 1,
+            multiplier: 1.0,
+        };
+        let fetcher = Arc::new(RetryFetcher::new(Arc::new(fetcher), retry, stats.clone()));
+        let robots = Arc::new(RobotsCache::new(fetcher.clone(), "data-scraper"));
+        update_with(fetcher, robots, &files).await.unwrap();
+        let updated = DataProcessor::<MobileRecord>::from_files(vec![&files.updated_vehicles]);
+        assert_eq!(
+            vec!["1001".to_string()],
+            updated.get_ids().iter().cloned().collect::<Vec<_>>()
+        );
+        let deleted = DataProcessor::<IDList>::from_files(vec![&files.deleted]);
+        assert_eq!(
+            vec!["1002".to_string()],
+            deleted.get_ids().iter().cloned().collect::<Vec<_>>()
+        );
+        let errors = DataProcessor::<DataError>::from_files(vec![&files.errors]);
+        assert_eq!(
+            vec!["1003".to_string()],
+            errors.get_ids().iter().cloned().collect::<Vec<_>>()
+        );
+        assert_eq!(1, stats.failed.load(Ordering::SeqCst));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    async fn print(rx: &mut Receiver<String>) -> Vec<String> {
         let stream = Box::pin(to_stream(rx));
         futures::pin_mut!(stream);
+        let mut payloads = vec![];
         while let Some(payload) = stream.next().await {
             info!("payload: {}", payload);
+            payloads.push(payload);
         }
+        payloads
     }
 }
@@ -1,4 +1,8 @@
 pub mod data_processor;
+pub mod dealer_migration;
 pub mod file_processor;
 pub mod mobile_bg_scraper;
+pub mod parse_file;
+pub mod price_backfill;
+pub mod run_status;
 pub mod stream_processor;
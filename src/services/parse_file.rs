@@ -0,0 +1,356 @@
+use std::{error::Error, fs, str::FromStr};
+
+use scraper::{ElementRef, Html};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    config::selectors::{Css, SELECTORS},
+    http::fetcher::decode_html,
+    model::records::MobileRecord,
+    scraper::{
+        dates::parse_timestamp,
+        details::{parse_details_with, ParseError},
+        mobile_bg::{get_header_data, listing_records, slink},
+        price::parse_price,
+        summary::parse_summary,
+    },
+    utils::helpers::extract_integers,
+};
+
+use super::run_status::RunStatus;
+
+/// The parser `parse-file` runs on a saved page.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FileKind {
+    Details,
+    Listing,
+    Search,
+}
+
+impl FromStr for FileKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "details" => Ok(FileKind::Details),
+            "listing" => Ok(FileKind::Listing),
+            "search" => Ok(FileKind::Search),
+            _ => Err(format!("Invalid page kind: {}", s)),
+        }
+    }
+}
+
+/// What the selector of a field matched.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldTrace {
+    pub field: String,
+    pub selector: String,
+    /// `None` when nothing matched.
+    pub raw: Option<String>,
+    pub error: Option<String>,
+}
+
+impl FieldTrace {
+    fn new(field: &str, selector: &str, raw: Option<String>) -> Self {
+        FieldTrace {
+            field: field.to_string(),
+            selector: selector.to_string(),
+            raw,
+            error: None,
+        }
+    }
+
+    /// Sets `error` when `failed` says the raw text could not be converted.
+    fn check(mut self, failed: impl Fn(&str) -> Option<String>) -> Self {
+        self.error = self.raw.as_deref().and_then(failed);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ParseReport {
+    pub kind: String,
+    /// The typed record, null when the page could not be parsed.
+    pub record: Value,
+    pub error: Option<String>,
+    pub fields: Vec<FieldTrace>,
+}
+
+/// Runs the parser of `kind` on a saved page and prints the report as JSON.
+pub fn parse_file(file_name: &str, kind: &str) -> Result<RunStatus, Box<dyn Error>> {
+    let kind = FileKind::from_str(kind)?;
+    // Pages saved by a browser are often UTF-8 but keep the windows-1251
+    // meta tag of the site.
+    let body = fs::read(file_name)?;
+    let html = match String::from_utf8(body) {
+        Ok(html) => html,
+        Err(e) => decode_html(e.as_bytes()),
+    };
+    let report = explain(&html, kind);
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(RunStatus::Completed)
+}
+
+pub fn explain(html: &str, kind: FileKind) -> ParseReport {
+    match kind {
+        FileKind::Details => explain_details(html),
+        FileKind::Listing => explain_listing(html),
+        FileKind::Search => explain_search(html),
+    }
+}
+
+/// The matches `parse_details` read, with the errors of their conversion.
+fn explain_details(html: &str) -> ParseReport {
+    let labels = &SELECTORS.labels;
+    let mut matches = vec![];
+    let result = parse_details_with("", html, &mut matches);
+    let mut fields = matches
+        .into_iter()
+        .map(|m| {
+            let trace = FieldTrace::new(m.field, &m.selector, m.raw);
+            match m.field {
+                "price" => trace.check(|raw| {
+                    let price = parse_price(raw);
+                    (price.amount == 0 && !price.on_request).then(|| "no amount".to_string())
+                }),
+                "view_count" => trace.check(|raw| {
+                    extract_integers(raw)
+                        .is_empty()
+                        .then(|| "no number".to_string())
+                }),
+                "published_at" => trace.check(|raw| no_time(raw, &labels.published)),
+                "edited_at" => trace.check(|raw| no_time(raw, &labels.edited)),
+                _ => trace,
+            }
+        })
+        .collect::<Vec<FieldTrace>>();
+    match result {
+        Ok(details) => {
+            for error in details.errors.iter() {
+                mark(&mut fields, error);
+            }
+            ParseReport {
+                kind: "details".to_string(),
+                record: serde_json::to_value(MobileRecord::from(details)).unwrap_or_default(),
+                error: None,
+                fields,
+            }
+        }
+        Err(e) => {
+            mark(&mut fields, &e);
+            ParseReport {
+                kind: "details".to_string(),
+                record: Value::Null,
+                error: Some(e.to_string()),
+                fields,
+            }
+        }
+    }
+}
+
+/// Sets the error on the trace of the field it is about.
+fn mark(fields: &mut [FieldTrace], error: &ParseError) {
+    let failed = match error {
+        ParseError::NotFound => return,
+        ParseError::Missing { field, .. } | ParseError::Invalid { field, .. } => *field,
+    };
+    for trace in fields.iter_mut().filter(|trace| trace.field == failed) {
+        trace.error = Some(error.to_string());
+    }
+}
+
+/// The selectors of the first row. A row without a link is not an advert.
+fn explain_listing(html: &str) -> ParseReport {
+    let selectors = &SELECTORS.listing;
+    let document = Html::parse_document(html);
+    let rows = document.select(&selectors.row).collect::<Vec<_>>();
+    let mut fields = vec![FieldTrace::new(
+        "row",
+        &selectors.row.css,
+        Some(format!("{} rows", rows.len())),
+    )];
+    if let Some(row) = rows
+        .iter()
+        .find(|row| row.select(&selectors.link).next().is_some())
+    {
+        fields.extend([
+            FieldTrace::new(
+                "link",
+                &selectors.link.css,
+                attr_of(row, &selectors.link, "href"),
+            ),
+            trace(row, "title", &selectors.link),
+            trace(row, "price", &selectors.price),
+            trace(row, "promoted", &selectors.promoted),
+            trace(row, "reserved", &selectors.sold),
+            trace(row, "summary", &selectors.summary),
+            trace(row, "promoted_summary", &selectors.promoted_summary),
+        ]);
+    }
+    let records = listing_records(html);
+    ParseReport {
+        kind: "listing".to_string(),
+        error: records.is_empty().then(|| "no adverts".to_string()),
+        record: serde_json::to_value(records).unwrap_or_default(),
+        fields,
+    }
+}
+
+fn explain_search(html: &str) -> ParseReport {
+    let selectors = &SELECTORS.search;
+    let document = Html::parse_document(html);
+    let root = document.root_element();
+    let summary = get_header_data(html)
+        .and_then(|description| parse_summary(&description).map_err(|e| e.into()));
+    let mut description = FieldTrace::new(
+        "description",
+        &selectors.description.css,
+        attr_of(&root, &selectors.description, "content"),
+    );
+    description.error = summary.as_ref().err().map(|e| e.to_string());
+    let fields = vec![
+        description,
+        FieldTrace::new(
+            "slink",
+            &selectors.slink.css,
+            attr_of(&root, &selectors.slink, "value"),
+        ),
+        FieldTrace::new(
+            "page_numbers",
+            &selectors.page_numbers.css,
+            all_of(&root, &selectors.page_numbers, |a| {
+                a.value().attr("href").map(|href| href.to_string())
+            }),
+        ),
+    ];
+    match summary {
+        Ok(summary) => {
+            let mut record = serde_json::to_value(summary).unwrap_or_default();
+            record["slink"] = Value::String(slink(html));
+            ParseReport {
+                kind: "search".to_string(),
+                record,
+                error: None,
+                fields,
+            }
+        }
+        Err(e) => ParseReport {
+            kind: "search".to_string(),
+            record: Value::Null,
+            error: Some(e.to_string()),
+            fields,
+        },
+    }
+}
+
+fn no_time(dates: &str, label: &str) -> Option<String> {
+    parse_timestamp(dates, label)
+        .is_none()
+        .then(|| format!("no time after '{}'", label))
+}
+
+fn trace(element: &ElementRef, field: &str, selector: &Css) -> FieldTrace {
+    FieldTrace::new(field, &selector.css, text_of(element, selector))
+}
+
+fn all_of(
+    element: &ElementRef,
+    selector: &Css,
+    value: impl Fn(ElementRef) -> Option<String>,
+) -> Option<String> {
+    let values = element
+        .select(selector)
+        .filter_map(value)
+        .collect::<Vec<_>>();
+    (!values.is_empty()).then(|| values.join("\n"))
+}
+
+fn text_of(element: &ElementRef, selector: &Css) -> Option<String> {
+    element
+        .select(selector)
+        .next()
+        .map(|e| e.text().collect::<Vec<_>>().join(" ").trim().to_string())
+}
+
+fn attr_of(element: &ElementRef, selector: &Css, name: &str) -> Option<String> {
+    element
+        .select(selector)
+        .next()
+        .and_then(|e| e.value().attr(name))
+        .map(|value| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::http::mock::fixture;
+
+    use super::{explain, FieldTrace, FileKind};
+
+    fn field<'a>(fields: &'a [FieldTrace], name: &str) -> &'a FieldTrace {
+        fields.iter().find(|f| f.field == name).unwrap()
+    }
+
+    #[test]
+    fn test_explain_details() {
+        let report = explain(&fixture("details.html"), FileKind::Details);
+        assert_eq!(None, report.error);
+        assert_eq!("Audi", report.record["make"]);
+        let price = field(&report.fields, "price");
+        assert_eq!("span#details_price", price.selector);
+        assert_eq!(Some("18 400 лв.".to_string()), price.raw);
+        assert_eq!(None, price.error);
+        assert_eq!(None, field(&report.fields, "dealer").raw);
+        // The urls as the parser keeps them, not as they are in the page.
+        let images = field(&report.fields, "images").raw.clone().unwrap();
+        assert!(images.starts_with("https://cdn.mobile.bg/photos/big/1001_"));
+
+        let html = fixture("details.html").replace("<li>Тип двигател</li><li>Дизелов</li>", "");
+        let report = explain(&html, FileKind::Details);
+        assert!(report.record.is_null());
+        assert_eq!(
+            Some("missing engine (ul.dilarData li)".to_string()),
+            report.error
+        );
+        assert_eq!(None, field(&report.fields, "engine").raw);
+        assert_eq!(
+            Some("18 400 лв.".to_string()),
+            field(&report.fields, "price").raw
+        );
+
+        let html = fixture("details.html").replace("юни 2010 г.", "32015");
+        let report = explain(&html, FileKind::Details);
+        assert_eq!(0, report.record["year"]);
+        assert_eq!(
+            Some("invalid year '32015' (ul.dilarData li)".to_string()),
+            field(&report.fields, "year").error
+        );
+
+        let html = fixture("details.html").replace("143 к.с.", "n/a");
+        let report = explain(&html, FileKind::Details);
+        assert_eq!(0, report.record["power"]);
+        let power = field(&report.fields, "power");
+        assert_eq!("ul.dilarData li 'Мощност'", power.selector);
+        assert_eq!(Some("n/a".to_string()), power.raw);
+        assert_eq!(
+            Some("invalid power 'n/a' (ul.dilarData li)".to_string()),
+            power.error
+        );
+    }
+
+    #[test]
+    fn test_explain_listing_and_search() {
+        let report = explain(&fixture("listing.html"), FileKind::Listing);
+        assert_eq!(None, report.error);
+        assert!(!report.record.as_array().unwrap().is_empty());
+        assert!(field(&report.fields, "link").raw.is_some());
+
+        let report = explain(&fixture("search.html"), FileKind::Search);
+        assert_eq!(25000, report.record["max_price"]);
+        assert_eq!("uafcc1", report.record["slink"]);
+
+        let report = explain(&fixture("details.html"), FileKind::Search);
+        assert!(report.record.is_null());
+        assert!(field(&report.fields, "description").error.is_some());
+    }
+}
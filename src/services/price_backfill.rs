@@ -0,0 +1,273 @@
+use std::{collections::BTreeSet, error::Error, fs};
+
+use csv::StringRecord;
+use log::{error, info};
+
+use crate::{
+    config::rates::{parse_date, ExchangeRates},
+    model::enums::Currency,
+};
+
+use super::run_status::RunStatus;
+
+/// Daily snapshots written by the scrape runs.
+pub fn snapshot_files(data_dir: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut files = fs::read_dir(data_dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("vehicle-") && name.ends_with(".csv"))
+        })
+        .filter_map(|path| path.to_str().map(|p| p.to_string()))
+        .collect::<Vec<String>>();
+    files.sort();
+    Ok(files)
+}
+
+/// Sets `price_bgn` and `price_eur` in the rows of the snapshot, adding the
+/// columns to old files, and leaves every other value as it was. Returns the
+/// number of rows that got both of them. Fails without touching the snapshot
+/// when a price has no rate for its currency and day.
+pub fn backfill_file(file_name: &str, rates: &ExchangeRates) -> Result<usize, Box<dyn Error>> {
+    let (mut headers, mut rows) = read_rows(file_name)?;
+    let price = column(&headers, "price", file_name)?;
+    let currency = column(&headers, "currency", file_name)?;
+    let created_on = column(&headers, "created_on", file_name)?;
+    let price_bgn = add_column(&mut headers, &mut rows, "price_bgn");
+    let price_eur = add_column(&mut headers, &mut rows, "price_eur");
+
+    let mut missing = BTreeSet::new();
+    let mut converted = 0;
+    for row in rows.iter_mut() {
+        let amount = row[price].parse::<u32>().unwrap_or_default();
+        let (bgn, eur) = match (
+            row[currency].parse::<Currency>(),
+            parse_date(&row[created_on]),
+        ) {
+            (Ok(from), Some(date)) if amount > 0 => rates.normalize(amount, from, date),
+            _ => (None, None),
+        };
+        if amount > 0 && (bgn.is_none() || eur.is_none()) {
+            missing.insert(format!("{} on {}", &row[currency], &row[created_on]));
+        }
+        if bgn.is_some() && eur.is_some() {
+            converted += 1;
+        }
+        let value = |amount: Option<u32>| amount.map(|a| a.to_string()).unwrap_or_default();
+        *row = with_value(row, price_bgn, &value(bgn));
+        *row = with_value(row, price_eur, &value(eur));
+    }
+    if !missing.is_empty() {
+        let missing = missing.into_iter().collect::<Vec<String>>();
+        return Err(format!("no exchange rate for {}", missing.join(", ")).into());
+    }
+    rewrite_rows(file_name, &headers, &rows)?;
+    Ok(converted)
+}
+
+/// The header and the rows of a snapshot as they are in the file, so they can
+/// be rewritten whatever version of `MobileRecord` wrote them.
+pub fn read_rows(file_name: &str) -> Result<(StringRecord, Vec<StringRecord>), Box<dyn Error>> {
+    let mut reader = csv::Reader::from_path(file_name)?;
+    let headers = reader.headers()?.clone();
+    let rows = reader
+        .records()
+        .collect::<Result<Vec<StringRecord>, csv::Error>>()?;
+    Ok((headers, rows))
+}
+
+/// Replaces the rows of the snapshot. They are written aside and renamed only
+/// when both files have as many rows as were read, so a failure leaves the
+/// snapshot as it was.
+pub fn rewrite_rows(
+    file_name: &str,
+    headers: &StringRecord,
+    rows: &[StringRecord],
+) -> Result<(), Box<dyn Error>> {
+    let in_file = count_rows(file_name)?;
+    if in_file != rows.len() {
+        return Err(format!(
+            "read {} of the {} rows of {}",
+            rows.len(),
+            in_file,
+            file_name
+        )
+        .into());
+    }
+    let tmp = format!("{}.tmp", file_name);
+    let mut writer = csv::Writer::from_path(&tmp)?;
+    writer.write_record(headers)?;
+    for row in rows.iter() {
+        writer.write_record(row)?;
+    }
+    writer.flush()?;
+    drop(writer);
+    let written = count_rows(&tmp)?;
+    if written != rows.len() {
+        fs::remove_file(&tmp)?;
+        return Err(format!(
+            "wrote {} of the {} rows of {}",
+            written,
+            rows.len(),
+            file_name
+        )
+        .into());
+    }
+    fs::rename(&tmp, file_name)?;
+    Ok(())
+}
+
+/// The rows of the file, including the ones that do not fit its header.
+fn count_rows(file_name: &str) -> Result<usize, Box<dyn Error>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_path(file_name)?;
+    let mut count = 0;
+    for row in reader.records() {
+        row?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+pub fn column(
+    headers: &StringRecord,
+    name: &str,
+    file_name: &str,
+) -> Result<usize, Box<dyn Error>> {
+    headers
+        .iter()
+        .position(|h| h == name)
+        .ok_or_else(|| format!("{} has no {} column", file_name, name).into())
+}
+
+/// The position of the column, appended with empty values when missing.
+fn add_column(headers: &mut StringRecord, rows: &mut [StringRecord], name: &str) -> usize {
+    if let Some(position) = headers.iter().position(|h| h == name) {
+        return position;
+    }
+    headers.push_field(name);
+    for row in rows.iter_mut() {
+        row.push_field("");
+    }
+    headers.len() - 1
+}
+
+/// The row with the value of one column replaced.
+pub fn with_value(row: &StringRecord, column: usize, value: &str) -> StringRecord {
+    row.iter()
+        .enumerate()
+        .map(|(i, v)| if i == column { value } else { v })
+        .collect()
+}
+
+pub fn backfill_prices(data_dir: &str, rates: &ExchangeRates) -> Result<RunStatus, Box<dyn Error>> {
+    let files = snapshot_files(data_dir)?;
+    let mut failed = 0;
+    for file_name in files.iter() {
+        match backfill_file(file_name, rates) {
+            Ok(converted) => info!("Back-filled {} prices in {}", converted, file_name),
+            Err(e) => {
+                error!("Failed to back-fill {}: {}", file_name, e);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(format!("{} of {} files failed", failed, files.len()).into());
+    }
+    Ok(RunStatus::Completed)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        config::rates::ExchangeRates,
+        model::{enums::Currency, records::MobileRecord},
+        services::file_processor::DataProcessor,
+        utils::helpers::create_empty_csv,
+        writer::persistance::{MobileData, MobileDataWriter},
+    };
+
+    use super::{backfill_file, backfill_prices, snapshot_files};
+
+    #[test]
+    fn test_backfill_prices() {
+        let dir = std::env::temp_dir().join("data-scraper-backfill");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let dir = dir.to_str().unwrap().to_string();
+        let snapshot = format!("{}/vehicle-2023-10-03.csv", dir);
+        let archive = format!("{}/vehicle.archive.csv", dir);
+        for file_name in [&snapshot, &archive] {
+            create_empty_csv::<MobileRecord>(file_name).unwrap();
+        }
+        let record = |id: &str, price: u32, currency: Currency, created_on: &str| MobileRecord {
+            id: id.to_string(),
+            price,
+            currency,
+            created_on: created_on.to_string(),
+            ..Default::default()
+        };
+        MobileData::Payload(vec![
+            record("1", 10000, Currency::USD, "2023-10-03"),
+            record("2", 10000, Currency::USD, "2020-01-01"),
+        ])
+        .write_csv(&snapshot, false)
+        .unwrap();
+
+        assert_eq!(vec![snapshot.clone()], snapshot_files(&dir).unwrap());
+        let rates = ExchangeRates::from_file("resources/test-data/rates/rates.csv").unwrap();
+        assert!(backfill_prices(&dir, &rates).is_err());
+        assert_eq!(
+            "no exchange rate for USD on 2020-01-01",
+            backfill_file(&snapshot, &rates).unwrap_err().to_string()
+        );
+        let records = DataProcessor::<MobileRecord>::from_files(vec![&snapshot]).get_values();
+        assert_eq!(2, records.len());
+        assert!(records.iter().all(|r| r.price_bgn.is_none()));
+
+        fs::remove_file(&snapshot).unwrap();
+        create_empty_csv::<MobileRecord>(&snapshot).unwrap();
+        MobileData::Payload(vec![record("1", 10000, Currency::USD, "2023-10-03")])
+            .write_csv(&snapshot, false)
+            .unwrap();
+        backfill_prices(&dir, &rates).unwrap();
+        let records = DataProcessor::<MobileRecord>::from_files(vec![&snapshot]).get_values();
+        assert_eq!(1, records.len());
+        assert_eq!(
+            (Some(18600), Some(9510)),
+            (records[0].price_bgn, records[0].price_eur)
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_backfill_legacy_header() {
+        let dir = std::env::temp_dir().join("data-scraper-backfill-legacy");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        // The header of this file has `promoted` and neither `top` nor `vip`.
+        let snapshot = dir.join("vehicle-2023-11-02.csv");
+        let snapshot = snapshot.to_str().unwrap();
+        fs::copy("resources/data/vehicle-2023-11-02.csv", snapshot).unwrap();
+        let before = fs::read_to_string(snapshot).unwrap();
+
+        let rates = ExchangeRates::from_file("resources/test-data/rates/rates.csv").unwrap();
+        assert!(backfill_file(snapshot, &rates).unwrap() > 0);
+        let after = fs::read_to_string(snapshot).unwrap();
+        assert_eq!(before.lines().count(), after.lines().count());
+        let first = |text: &str| text.lines().nth(1).unwrap().to_string();
+        assert_eq!(format!("{},18400,9408", first(&before)), first(&after));
+        assert!(after
+            .lines()
+            .next()
+            .unwrap()
+            .ends_with(",deleted_on,price_bgn,price_eur"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,32 @@
+use std::fmt;
+
+/// How a scraper run ended.
+#[derive(Debug, PartialEq, Clone)]
+pub enum RunStatus {
+    Completed,
+    /// The site kept serving block pages, the reason says how many.
+    Aborted(String),
+    /// Required fields were missing from too many pages, the site has likely
+    /// changed its markup.
+    Drifted(String),
+}
+
+impl RunStatus {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            RunStatus::Completed => 0,
+            RunStatus::Aborted(_) => 2,
+            RunStatus::Drifted(_) => 3,
+        }
+    }
+}
+
+impl fmt::Display for RunStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunStatus::Completed => write!(f, "completed"),
+            RunStatus::Aborted(reason) => write!(f, "aborted ({})", reason),
+            RunStatus::Drifted(fields) => write!(f, "parser drift ({})", fields),
+        }
+    }
+}
@@ -90,9 +90,20 @@ pub mod crossbeam_utils {
 
     use futures::Stream;
 
-    pub fn to_stream<T>(rx: &mut Receiver<T>) -> impl Stream<Item = T> + '_ {
+    /// The blocking `recv` runs on a blocking thread that forwards the items,
+    /// so the runtime is never blocked while waiting for them.
+    pub fn to_stream<T: Send + 'static>(rx: &mut Receiver<T>) -> impl Stream<Item = T> + '_ {
+        let rx = rx.clone();
+        let (tx, mut items) = tokio::sync::mpsc::unbounded_channel();
+        tokio::task::spawn_blocking(move || {
+            while let Ok(item) = rx.recv() {
+                if tx.send(item).is_err() {
+                    break;
+                }
+            }
+        });
         async_stream::stream! {
-            while let Ok(item) = rx.recv()  {
+            while let Some(item) = items.recv().await {
                 yield item;
             }
         }
@@ -203,7 +214,7 @@ pub fn extract_ascii_latin(text: &str) -> String {
 mod tests {
     use std::collections::HashSet;
 
-    use crate::scraper::mobile_bg::{get_pages, slink};
+    use crate::{http::mock::fixture, scraper::mobile_bg::slink};
 
     use super::*;
 
@@ -244,11 +255,11 @@ mod tests {
         let url = mobile_search_url(crate::LISTING_URL, "1", "", SaleType::SOLD, 0, 5000);
         assert_eq!(
             url,
-            "https://www.mobile.bg/pcgi/mobile.cgi?act=3&topmenu=1&rub=1&f1=1&f94=1~%CA%E0%EF%E0%F0%E8%F0%E0%ED%5C%CF%F0%EE%E4%E0%E4%E5%ED&f7=0&f8=5000"
+            "https://www.mobile.bg/pcgi/mobile.cgi?act=3&topmenu=1&rub=1&f1=1&f94=1~%CA%E0%EF%E0%F0%E8%F0%E0%ED%5C%CF%F0%EE%E4%E0%E4%E5%ED&f8=5000"
         );
-        let html = get_pages(&url).unwrap();
-        // info!("content: {}", html);
+        let html = fixture("search.html");
         let slink = slink(&html);
         info!("slink: {}", slink);
+        assert_eq!(slink, "uafcc1");
     }
 }
@@ -66,7 +66,7 @@ mod tests {
     }
 
     fn read_file_from_resources(filename: &str) -> Result<String> {
-        let path = format!("resources/{}", filename);
+        let path = format!("resources/test-data/csv/{}", filename);
         fs::read_to_string(path)
     }
 
@@ -102,6 +102,7 @@ mod tests {
         let data = MobileData::Payload(vehicles);
 
         let test_file = "test.json";
+        File::create(test_file)?;
         data.write_json(test_file)?;
 
         let json_data = fs::read_to_string(test_file)?;
@@ -129,6 +130,7 @@ mod tests {
         let data = MobileData::Payload(vehicles);
 
         let test_file = "test.csv";
+        File::create(test_file)?;
         data.write_csv(test_file, true)?;
 
         let csv_data = fs::read_to_string(test_file)?;
@@ -156,11 +158,12 @@ mod tests {
         ];
         let data = MobileData::Payload(vehicles);
 
-        let test_file = "test.csv";
+        let test_file = "test_append.csv";
+        File::create(test_file)?;
         data.write_csv(test_file, true)?;
 
         let csv_data = fs::read_to_string(test_file)?;
-        let expected_csv_data = read_file_from_resources(test_file)?;
+        let expected_csv_data = read_file_from_resources("test.csv")?;
         assert_eq!(csv_data, expected_csv_data);
 
         let new_vehicles = vec![